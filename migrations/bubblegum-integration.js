@@ -125,7 +125,7 @@ var MenagerieMinter = /** @class */ (function () {
                     case 2:
                         rarityState = (_d.sent())[0];
                         _b = (_a = this.rarityProgram.methods
-                            .validateMint(new anchor_1.BN(nftIndex), minRarityPercentage))
+                            .validateMint(minRarityPercentage, new anchor_1.BN(nftIndex), false))
                             .accounts;
                         _c = {
                             state: rarityState,
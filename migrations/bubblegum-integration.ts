@@ -140,7 +140,7 @@ export class MenagerieMinter {
       
       // Call the validate_mint function
       const result = await this.rarityProgram.methods
-        .validateMint(new BN(nftIndex), minRarityPercentage)
+        .validateMint(minRarityPercentage, new BN(nftIndex), false)
         .accounts({
           state: rarityState,
           merkleTree: this.ADDRESSES.storageAccount,
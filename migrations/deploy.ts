@@ -98,10 +98,11 @@ export class JitoBundler {
   const tx = new Transaction().add(...decompiled.instructions);
   // Add validate mint instruction
   tx.add(await this.program.methods
-    .validateMint(minRarityThreshold)
+    .validateCandyMachineMint(minRarityThreshold, false)
     .accounts({
       feeReceiver: new PublicKey("89VB5UmvopuCFmp5Mf8YPX28fGvvqn79afCgouQuPyhY"),
       state: rarityStatePDA,
+      merkleTree: candyMachineId,
       candyMachine: candyMachineId,
       recentSlothashes: SYSVAR_SLOT_HASHES_PUBKEY,
       minter: this.wallet.publicKey,
@@ -177,10 +178,11 @@ export class JitoBundler {
     tx.instructions[tx.instructions.length-1].keys.push({pubkey:new PublicKey("CVkDzSp18UpKzhTtryrtHg14VN5QrxuYhrUMjbwQ4z9G"), isSigner:false, isWritable:true})
     // Add validate mint instruction
     tx.add(await this.program.methods
-      .validateMint(minRarityPercentage)
+      .validateCandyMachineMint(minRarityPercentage, false)
       .accounts({
         feeReceiver: new PublicKey("89VB5UmvopuCFmp5Mf8YPX28fGvvqn79afCgouQuPyhY"),
         state: nftBeaterAddress,
+        merkleTree: candyMachineAddress,
         candyMachine: candyMachineAddress,
         recentSlothashes: SYSVAR_SLOT_HASHES_PUBKEY,
         minter: this.wallet.publicKey,
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use solana_program::{
+    instruction::Instruction,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+use crate::state::{
+    AdapterInstruction, IndexLocation, LaunchpadAdapter, MAX_ADAPTER_INSTRUCTIONS, MAX_DISCRIMINATOR_LENGTH,
+};
+use crate::ErrorCode;
+
+// Check that adapter instructions are well formed before storing them
+pub fn validate_adapter_instructions(instructions: &[AdapterInstruction]) -> Result<()> {
+    if instructions.len() > MAX_ADAPTER_INSTRUCTIONS {
+        msg!("At most {} adapter instructions are supported", MAX_ADAPTER_INSTRUCTIONS);
+        return Err(error!(ErrorCode::InvalidLaunchpadAdapter));
+    }
+
+    for instruction in instructions.iter() {
+        if instruction.discriminator.is_empty() || instruction.discriminator.len() > MAX_DISCRIMINATOR_LENGTH {
+            msg!("Discriminators must be 1 to {} bytes", MAX_DISCRIMINATOR_LENGTH);
+            return Err(error!(ErrorCode::InvalidLaunchpadAdapter));
+        }
+
+        if let IndexLocation::InstructionData { width, .. } = instruction.location {
+            if width == 0 || width > 8 {
+                msg!("Index width must be 1 to 8 bytes");
+                return Err(error!(ErrorCode::InvalidLaunchpadAdapter));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Load the instructions of the current transaction that run before ours, closest first.
+// Later instructions have not executed yet, so they can never prove a mint.
+pub fn preceding_instructions(ix_sysvar: &AccountInfo) -> Result<Vec<Instruction>> {
+    let current = load_current_index_checked(ix_sysvar)? as usize;
+    let mut instructions = Vec::with_capacity(current);
+    for position in (0..current).rev() {
+        instructions.push(load_instruction_at_checked(position, ix_sysvar)?);
+    }
+    Ok(instructions)
+}
+
+// Find the closest earlier instruction in the current transaction that the adapter recognizes
+pub fn find_adapter_instruction<'a>(
+    ix_sysvar: &AccountInfo,
+    adapter: &'a LaunchpadAdapter,
+) -> Result<(Instruction, &'a AdapterInstruction)> {
+    for ix in preceding_instructions(ix_sysvar)? {
+        if ix.program_id != adapter.program_id {
+            continue;
+        }
+
+        let matched = adapter
+            .instructions
+            .iter()
+            .find(|i| ix.data.starts_with(&i.discriminator));
+        if let Some(adapter_instruction) = matched {
+            return Ok((ix, adapter_instruction));
+        }
+    }
+
+    msg!("No recognized {} instruction found before this one", adapter.program_id);
+    Err(error!(ErrorCode::MintTransactionAnalysisFailed))
+}
+
+// Read a little-endian unsigned integer of `width` bytes at `offset`
+pub fn read_index_from_data(data: &[u8], offset: u16, width: u8) -> Result<u64> {
+    let start = offset as usize;
+    let end = start + width as usize;
+    if width == 0 || width > 8 || data.len() < end {
+        msg!("Instruction data too short for index at {}..{}", start, end);
+        return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+    }
+
+    let mut bytes = [0u8; 8];
+    bytes[..width as usize].copy_from_slice(&data[start..end]);
+    Ok(u64::from_le_bytes(bytes))
+}
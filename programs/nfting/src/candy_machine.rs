@@ -0,0 +1,320 @@
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+
+use crate::ErrorCode;
+
+// Account discriminator of a Candy Machine v3 account
+pub const CANDY_MACHINE_DISCRIMINATOR: [u8; 8] = [51, 173, 177, 113, 25, 241, 109, 189];
+
+// Candy Machine v3 size limits
+const MAX_SYMBOL_LENGTH: usize = 10;
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_URI_LENGTH: usize = 200;
+const MAX_CREATOR_LIMIT: usize = 5;
+const MAX_CREATOR_LEN: usize = 32 + 1 + 1;
+
+// Offset of the config line section, which follows the fixed-size CandyMachine struct
+pub const HIDDEN_SECTION: usize = 8 + // discriminator
+    1 + // version
+    1 + // token standard
+    6 + // features
+    32 + // authority
+    32 + // mint authority
+    32 + // collection mint
+    8 + // items redeemed
+    8 + // items available (config data)
+    4 + MAX_SYMBOL_LENGTH + // u32 + max symbol length
+    2 + // seller fee basis points
+    8 + // max supply
+    1 + // is mutable
+    4 + MAX_CREATOR_LIMIT * MAX_CREATOR_LEN + // u32 + creators vec
+    1 + // option (config lines settings)
+    4 + MAX_NAME_LENGTH + // u32 + max name length
+    4 + // name length
+    4 + MAX_URI_LENGTH + // u32 + max uri length
+    4 + // uri length
+    1 + // is sequential
+    1 + // option (hidden setting)
+    4 + MAX_NAME_LENGTH + // u32 + max name length
+    4 + MAX_URI_LENGTH + // u32 + max uri length
+    32; // hash
+
+// Patterns replaced with the mint number in hidden settings and name/URI prefixes
+const NUMBER_PATTERN_ID: &str = "$ID$";
+const NUMBER_PATTERN_ID_PLUS_ONE: &str = "$ID+1$";
+
+// Candy Machine v3 account (mirrors mpl-candy-machine-core)
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+pub struct CandyMachine {
+    pub version: u8,
+    pub token_standard: u8,
+    pub features: [u8; 6],
+    pub authority: Pubkey,
+    pub mint_authority: Pubkey,
+    pub collection_mint: Pubkey,
+    pub items_redeemed: u64,
+    pub data: CandyMachineData,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+pub struct CandyMachineData {
+    pub items_available: u64,
+    pub symbol: String,
+    pub seller_fee_basis_points: u16,
+    pub max_supply: u64,
+    pub is_mutable: bool,
+    pub creators: Vec<Creator>,
+    pub config_line_settings: Option<ConfigLineSettings>,
+    pub hidden_settings: Option<HiddenSettings>,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub percentage_share: u8,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+pub struct ConfigLineSettings {
+    pub prefix_name: String,
+    pub name_length: u32,
+    pub prefix_uri: String,
+    pub uri_length: u32,
+    pub is_sequential: bool,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+pub struct HiddenSettings {
+    pub name: String,
+    pub uri: String,
+    pub hash: [u8; 32],
+}
+
+// Name and URI the next mint will receive
+pub struct ConfigLine {
+    pub name: String,
+    pub uri: String,
+}
+
+// Deserialize a Candy Machine v3 account
+pub fn load_candy_machine(data: &[u8]) -> Result<CandyMachine> {
+    if data.len() < HIDDEN_SECTION || data[0..8] != CANDY_MACHINE_DISCRIMINATOR {
+        msg!("Account is not a Candy Machine");
+        return Err(error!(ErrorCode::InvalidCandyMachine));
+    }
+
+    CandyMachine::deserialize(&mut &data[8..]).map_err(|_| {
+        msg!("Failed to deserialize Candy Machine");
+        error!(ErrorCode::InvalidCandyMachine)
+    })
+}
+
+// Resolve the config line the next mint in this slot will receive.
+// This replays Candy Machine's own selection: a slot hash minus timestamp seed
+// picks a position in the remaining indices, which maps to a config line.
+pub fn predict_next_config_line(
+    candy_machine: &CandyMachine,
+    data: &[u8],
+    slot_hashes: &[u8],
+    unix_timestamp: i64,
+) -> Result<ConfigLine> {
+    let items_available = candy_machine.data.items_available;
+    let mint_number = candy_machine.items_redeemed;
+
+    if mint_number >= items_available {
+        msg!("Candy Machine is empty");
+        return Err(error!(ErrorCode::CandyMachineEmpty));
+    }
+
+    // Hidden settings give every mint the same name and URI apart from the mint number
+    if let Some(hidden_settings) = &candy_machine.data.hidden_settings {
+        return Ok(ConfigLine {
+            name: replace_patterns(&hidden_settings.name, mint_number as usize),
+            uri: replace_patterns(&hidden_settings.uri, mint_number as usize),
+        });
+    }
+
+    let settings = match &candy_machine.data.config_line_settings {
+        Some(settings) => settings,
+        None => {
+            msg!("Candy Machine has neither config line nor hidden settings");
+            return Err(error!(ErrorCode::InvalidCandyMachine));
+        }
+    };
+
+    let name_length = settings.name_length as usize;
+    let uri_length = settings.uri_length as usize;
+    let config_line_size = name_length + uri_length;
+
+    // All config lines must be loaded before Candy Machine allows minting
+    if data.len() < HIDDEN_SECTION + 4 {
+        return Err(error!(ErrorCode::InvalidCandyMachine));
+    }
+    let config_count = u32::from_le_bytes(*array_ref![data, HIDDEN_SECTION, 4]) as u64;
+    if config_count != items_available {
+        msg!("Candy Machine has {} of {} config lines loaded", config_count, items_available);
+        return Err(error!(ErrorCode::CandyMachineNotFullyLoaded));
+    }
+
+    let value_to_use = if settings.is_sequential {
+        mint_number as usize
+    } else {
+        // Same seed Candy Machine derives in its mint instruction
+        if slot_hashes.len() < 20 {
+            return Err(error!(ErrorCode::InvalidCandyMachine));
+        }
+        let most_recent = array_ref![slot_hashes, 12, 8];
+        let seed = u64::from_le_bytes(*most_recent).saturating_sub(unix_timestamp as u64);
+        let remainder = (seed % (items_available - mint_number)) as usize;
+
+        // The remaining indices follow the config lines and the mint bitmask
+        let indices_start = HIDDEN_SECTION
+            + 4
+            + (items_available as usize) * config_line_size
+            + (items_available as usize / 8)
+            + 1;
+        let mint_index = indices_start + remainder * 4;
+        if data.len() < mint_index + 4 {
+            return Err(error!(ErrorCode::InvalidCandyMachine));
+        }
+
+        u32::from_le_bytes(*array_ref![data, mint_index, 4]) as usize
+    };
+    msg!("Next mint uses config line {}", value_to_use);
+
+    let position = HIDDEN_SECTION + 4 + value_to_use * config_line_size;
+    if data.len() < position + config_line_size {
+        return Err(error!(ErrorCode::InvalidCandyMachine));
+    }
+
+    let name = read_config_string(&data[position..position + name_length]);
+    let uri = read_config_string(&data[position + name_length..position + config_line_size]);
+
+    Ok(ConfigLine {
+        name: replace_patterns(&settings.prefix_name, value_to_use) + &name,
+        uri: replace_patterns(&settings.prefix_uri, value_to_use) + &uri,
+    })
+}
+
+// Config line strings are stored null-padded to their fixed length
+fn read_config_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string()
+}
+
+fn replace_patterns(value: &str, index: usize) -> String {
+    value
+        .replace(NUMBER_PATTERN_ID_PLUS_ONE, &(index + 1).to_string())
+        .replace(NUMBER_PATTERN_ID, &index.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME_LENGTH: usize = 4;
+    const URI_LENGTH: usize = 8;
+
+    fn push_string(data: &mut Vec<u8>, value: &str) {
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(value.as_bytes());
+    }
+
+    fn padded(value: &str, length: usize) -> Vec<u8> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(length, 0);
+        bytes
+    }
+
+    // Candy Machine account with one config line per item ("<i>", "<i>.json") and the given
+    // remaining indices, laid out the way Candy Machine Core stores them
+    fn candy_machine_account(items_redeemed: u64, is_sequential: bool, remaining: &[u32]) -> Vec<u8> {
+        let items_available = remaining.len() as u64;
+        let mut data = CANDY_MACHINE_DISCRIMINATOR.to_vec();
+        data.extend([0, 0]); // version, token standard
+        data.extend([0; 6]); // features
+        data.extend([0; 96]); // authority, mint authority, collection mint
+        data.extend(items_redeemed.to_le_bytes());
+        data.extend(items_available.to_le_bytes());
+        push_string(&mut data, "TEST");
+        data.extend(500u16.to_le_bytes()); // seller fee basis points
+        data.extend(0u64.to_le_bytes()); // max supply
+        data.push(1); // is mutable
+        data.extend(0u32.to_le_bytes()); // creators
+        data.push(1); // config line settings
+        push_string(&mut data, "Item #");
+        data.extend((NAME_LENGTH as u32).to_le_bytes());
+        push_string(&mut data, "https://example.com/");
+        data.extend((URI_LENGTH as u32).to_le_bytes());
+        data.push(is_sequential as u8);
+        data.push(0); // no hidden settings
+        data.resize(HIDDEN_SECTION, 0);
+        
+        data.extend((items_available as u32).to_le_bytes());
+        for i in 0..items_available {
+            data.extend(padded(&i.to_string(), NAME_LENGTH));
+            data.extend(padded(&format!("{}.json", i), URI_LENGTH));
+        }
+        data.extend(vec![0; items_available as usize / 8 + 1]);
+        for index in remaining {
+            data.extend(index.to_le_bytes());
+        }
+        data
+    }
+
+    // SlotHashes sysvar data: entry count, then (slot, hash) pairs. Candy Machine reads its
+    // seed from bytes 12..20, the high half of the newest slot and the start of its hash.
+    fn slot_hashes(seed_bytes: u64) -> Vec<u8> {
+        let mut data = vec![0; 48];
+        data[0..8].copy_from_slice(&1u64.to_le_bytes());
+        data[12..20].copy_from_slice(&seed_bytes.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn hidden_section_offset() {
+        // Fixed size of the Candy Machine Core struct before the config lines
+        assert_eq!(HIDDEN_SECTION, 850);
+    }
+
+    #[test]
+    fn random_selection_vector() {
+        // 2 of 5 items minted, the remaining indices are [4, 1, 3]
+        let data = candy_machine_account(2, false, &[4, 1, 3, 0, 2]);
+        let candy_machine = load_candy_machine(&data).unwrap();
+        
+        // seed = 1_000_007 - 1_000_000 = 7, 7 % (5 - 2) = 1, remaining[1] = config line 1
+        let line = predict_next_config_line(&candy_machine, &data, &slot_hashes(1_000_007), 1_000_000).unwrap();
+        assert_eq!(line.name, "Item #1");
+        assert_eq!(line.uri, "https://example.com/1.json");
+        
+        // seed = 9 -> 9 % 3 = 0 -> config line 4
+        let line = predict_next_config_line(&candy_machine, &data, &slot_hashes(1_000_009), 1_000_000).unwrap();
+        assert_eq!(line.name, "Item #4");
+        
+        // A timestamp past the slot hash value saturates the seed at 0
+        let line = predict_next_config_line(&candy_machine, &data, &slot_hashes(5), 1_000_000).unwrap();
+        assert_eq!(line.name, "Item #4");
+    }
+
+    #[test]
+    fn sequential_selection() {
+        let data = candy_machine_account(3, true, &[0, 1, 2, 3, 4]);
+        let candy_machine = load_candy_machine(&data).unwrap();
+        let line = predict_next_config_line(&candy_machine, &data, &slot_hashes(1_000_007), 1_000_000).unwrap();
+        assert_eq!(line.name, "Item #3");
+        assert_eq!(line.uri, "https://example.com/3.json");
+    }
+
+    #[test]
+    fn empty_candy_machine() {
+        let data = candy_machine_account(5, false, &[4, 1, 3, 0, 2]);
+        let candy_machine = load_candy_machine(&data).unwrap();
+        assert!(predict_next_config_line(&candy_machine, &data, &slot_hashes(7), 0).is_err());
+    }
+}
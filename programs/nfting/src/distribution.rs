@@ -0,0 +1,309 @@
+use crate::results::{rarity_tier, IndexRange};
+use crate::state::{RarityState, SCORE_HISTOGRAM_BUCKETS, UNKNOWN_SCORE, UNKNOWN_PRECISE_SCORE};
+
+// Histogram bucket of a percentage score; scores above 100 share the top bucket
+pub fn histogram_bucket(percent: u8) -> usize {
+    (percent as usize).min(SCORE_HISTOGRAM_BUCKETS - 1)
+}
+
+// Count the items of a state's rarity map per percentage score, leaving out unknown scores
+pub fn score_histogram(state: &RarityState) -> Vec<u32> {
+    let unknown = state.unknown_score();
+    let mut histogram = vec![0u32; SCORE_HISTOGRAM_BUCKETS];
+    for score in state.scores().filter(|score| *score != unknown) {
+        histogram[histogram_bucket(state.score_percent(score))] += 1;
+    }
+    histogram
+}
+
+// Count the known items of a u16 state's rarity map per tier (index 0 = Common)
+pub fn precise_tier_counts(state: &RarityState) -> Vec<u32> {
+    let mut tier_counts = vec![0u32; state.precise_thresholds.len() + 1];
+    for score in state.scores().filter(|score| *score != UNKNOWN_PRECISE_SCORE) {
+        tier_counts[rarity_tier(&state.precise_thresholds, score) as usize] += 1;
+    }
+    tier_counts
+}
+
+// Whether a u16 state's tier counts match its current thresholds
+fn has_precise_tier_counts(state: &RarityState) -> bool {
+    state.is_precise() && state.precise_tier_counts.len() == state.precise_thresholds.len() + 1
+}
+
+// Build the score histogram and precise tier counts for states created before they existed
+pub fn ensure_histogram(state: &mut RarityState) {
+    if state.score_histogram.len() != SCORE_HISTOGRAM_BUCKETS {
+        state.score_histogram = score_histogram(state);
+    }
+    if state.is_precise() && !has_precise_tier_counts(state) {
+        state.precise_tier_counts = precise_tier_counts(state);
+    }
+}
+
+// Extend the rarity map to hold end_index entries; new entries start out unknown
+// (every byte of the unknown marker is 0xFF for both score widths)
+pub fn grow_rarity_map(state: &mut RarityState, end_index: usize) {
+    if end_index > state.map_len() {
+        let size = end_index * state.entry_width();
+        state.rarity_map.resize(size, UNKNOWN_SCORE);
+    }
+}
+
+// Ranges of indices from start_index on whose score is unknown, at most max_ranges of them,
+// and the index to continue from when there are more
+pub fn unknown_ranges(state: &RarityState, start_index: usize, max_ranges: usize) -> (Vec<IndexRange>, Option<u32>) {
+    let unknown = state.unknown_score();
+    let mut ranges: Vec<IndexRange> = Vec::new();
+    
+    for (index, score) in state.scores().enumerate().skip(start_index) {
+        if score != unknown {
+            continue;
+        }
+        if let Some(range) = ranges.last_mut().filter(|range| range.end_index as usize == index) {
+            range.end_index += 1;
+        } else if ranges.len() == max_ranges {
+            return (ranges, Some(index as u32));
+        } else {
+            ranges.push(IndexRange { start_index: index as u32, end_index: index as u32 + 1 });
+        }
+    }
+    
+    (ranges, None)
+}
+
+// Write the score of an index, moving it between histogram buckets and precise tiers
+pub fn write_score(state: &mut RarityState, index: usize, score: u16) {
+    let unknown = state.unknown_score();
+    let previous = state.score(index as u64).unwrap_or(unknown);
+    let track_tiers = has_precise_tier_counts(state);
+    if previous != unknown {
+        let bucket = histogram_bucket(state.score_percent(previous));
+        state.score_histogram[bucket] -= 1;
+        if track_tiers {
+            state.precise_tier_counts[rarity_tier(&state.precise_thresholds, previous) as usize] -= 1;
+        }
+    }
+    if score != unknown {
+        let bucket = histogram_bucket(state.score_percent(score));
+        state.score_histogram[bucket] += 1;
+        if track_tiers {
+            state.precise_tier_counts[rarity_tier(&state.precise_thresholds, score) as usize] += 1;
+        }
+    }
+    state.set_score(index, score);
+}
+
+// Number of items meeting a minimum score (in the state's score unit) and the number of
+// items in each rarity tier (index 0 = Common). Percentage scores are counted from the
+// histogram. Basis-point scores use the precise tier counts, and the histogram for whole
+// percentages; only other minimums fall between buckets and scan the map.
+// A minimum above the state's highest score is never met.
+pub fn passing_and_tier_counts(state: &RarityState, histogram: &[u32], min_score: u16) -> (u32, Vec<u32>) {
+    let thresholds = state.thresholds();
+    let mut tier_counts = vec![0u32; thresholds.len() + 1];
+    let mut passing = 0u32;
+    let reachable = min_score <= state.max_score();
+    
+    if state.is_precise() {
+        tier_counts = if has_precise_tier_counts(state) {
+            state.precise_tier_counts.clone()
+        } else {
+            precise_tier_counts(state)
+        };
+        
+        if !reachable {
+            passing = 0;
+        } else if min_score % 100 == 0 {
+            passing = histogram[(min_score / 100) as usize..].iter().sum();
+        } else if thresholds.contains(&min_score) {
+            // Items meeting a threshold are exactly those in tiers past every threshold up to it
+            let tier = thresholds.iter().filter(|threshold| **threshold <= min_score).count();
+            passing = tier_counts[tier..].iter().sum();
+        } else {
+            passing = state.scores().filter(|score| *score != UNKNOWN_PRECISE_SCORE && *score >= min_score).count() as u32;
+        }
+    } else {
+        for (score, &count) in histogram.iter().enumerate() {
+            tier_counts[rarity_tier(&thresholds, score as u16) as usize] += count;
+            if reachable && score as u16 >= min_score {
+                passing += count;
+            }
+        }
+    }
+    
+    (passing, tier_counts)
+}
+
+// Chance in basis points that one of `total` items is among `count`
+pub fn probability_bps(count: u32, total: u32) -> u16 {
+    if total == 0 {
+        0
+    } else {
+        (count as u64 * 10_000 / total as u64) as u16
+    }
+}
+
+// Chance in basis points that an item reaches each tier or a higher one (index 0 = Common)
+pub fn tier_pass_probabilities(tier_counts: &[u32], total: u32) -> Vec<u16> {
+    let mut reaching = 0u32;
+    let mut probabilities: Vec<u16> = tier_counts
+        .iter()
+        .rev()
+        .map(|count| {
+            reaching += count;
+            probability_bps(reaching, total)
+        })
+        .collect();
+    probabilities.reverse();
+    probabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MAX_PRECISE_SCORE;
+
+    // Percentage state with tiers at [50, 75, 90] and the given scores
+    fn percent_state(scores: &[u8]) -> RarityState {
+        let mut state = RarityState {
+            score_width: 1,
+            rarity_thresholds: vec![50, 75, 90],
+            ..Default::default()
+        };
+        upload(&mut state, &scores.iter().map(|score| *score as u16).collect::<Vec<_>>());
+        state
+    }
+
+    // Basis-point state with tiers at [5000, 7500, 9000] and the given scores
+    fn precise_state(scores: &[u16]) -> RarityState {
+        let mut state = RarityState {
+            score_width: 2,
+            precise_thresholds: vec![5_000, 7_500, 9_000],
+            ..Default::default()
+        };
+        upload(&mut state, scores);
+        state
+    }
+
+    // Write scores the way the upload instructions do
+    fn upload(state: &mut RarityState, scores: &[u16]) {
+        ensure_histogram(state);
+        grow_rarity_map(state, scores.len());
+        for (index, score) in scores.iter().enumerate() {
+            write_score(state, index, *score);
+        }
+    }
+
+    #[test]
+    fn bucket_boundaries() {
+        assert_eq!(histogram_bucket(0), 0);
+        assert_eq!(histogram_bucket(99), 99);
+        assert_eq!(histogram_bucket(100), 100);
+        assert_eq!(histogram_bucket(101), 100);
+        assert_eq!(histogram_bucket(u8::MAX), 100);
+    }
+
+    #[test]
+    fn histogram_leaves_out_unknown_scores() {
+        let state = percent_state(&[0, 100, UNKNOWN_SCORE, 50, 50]);
+        let histogram = score_histogram(&state);
+        assert_eq!(histogram.len(), SCORE_HISTOGRAM_BUCKETS);
+        assert_eq!(histogram[0], 1);
+        assert_eq!(histogram[50], 2);
+        assert_eq!(histogram[100], 1);
+        assert_eq!(histogram.iter().sum::<u32>(), 4);
+        assert_eq!(state.score_histogram, histogram);
+    }
+
+    #[test]
+    fn precise_histogram_buckets_by_percent() {
+        let state = precise_state(&[0, 99, 100, 9_999, MAX_PRECISE_SCORE, UNKNOWN_PRECISE_SCORE]);
+        assert_eq!(state.score_histogram[0], 2);
+        assert_eq!(state.score_histogram[1], 1);
+        assert_eq!(state.score_histogram[99], 1);
+        assert_eq!(state.score_histogram[100], 1);
+        assert_eq!(state.precise_tier_counts, vec![3, 0, 0, 2]);
+        assert_eq!(state.precise_tier_counts, precise_tier_counts(&state));
+    }
+
+    #[test]
+    fn write_score_moves_counts() {
+        let mut state = precise_state(&[4_999, 5_000]);
+        assert_eq!(state.precise_tier_counts, vec![1, 1, 0, 0]);
+        
+        write_score(&mut state, 0, 9_000);
+        assert_eq!(state.score_histogram[49], 0);
+        assert_eq!(state.score_histogram[90], 1);
+        assert_eq!(state.precise_tier_counts, vec![0, 1, 0, 1]);
+        
+        write_score(&mut state, 1, UNKNOWN_PRECISE_SCORE);
+        assert_eq!(state.score_histogram[50], 0);
+        assert_eq!(state.precise_tier_counts, vec![0, 0, 0, 1]);
+        assert_eq!(state.score_histogram, score_histogram(&state));
+    }
+
+    #[test]
+    fn new_entries_start_unknown() {
+        let mut state = precise_state(&[7_500]);
+        grow_rarity_map(&mut state, 4);
+        assert_eq!(state.map_len(), 4);
+        assert_eq!(state.score(3), Some(UNKNOWN_PRECISE_SCORE));
+        
+        let (ranges, next) = unknown_ranges(&state, 0, 8);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!((ranges[0].start_index, ranges[0].end_index), (1, 4));
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn percent_passing_and_tiers() {
+        let state = percent_state(&[10, 50, 74, 75, 90, 100, UNKNOWN_SCORE]);
+        let histogram = state.score_histogram.clone();
+        
+        let (passing, tier_counts) = passing_and_tier_counts(&state, &histogram, 75);
+        assert_eq!(passing, 3);
+        assert_eq!(tier_counts, vec![1, 2, 1, 2]);
+        
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 0).0, 6);
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 100).0, 1);
+        
+        // Unknown scores sit above 100 but never count as meeting a higher minimum
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 101).0, 0);
+    }
+
+    #[test]
+    fn precise_passing_paths() {
+        let state = precise_state(&[4_999, 5_000, 7_499, 7_500, 8_050, 10_000, UNKNOWN_PRECISE_SCORE]);
+        let histogram = state.score_histogram.clone();
+        
+        // Whole percent (histogram), threshold (tier counts) and other minimums (map scan)
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 5_000).0, 5);
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 7_500).0, 3);
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 8_051).0, 1);
+        assert_eq!(passing_and_tier_counts(&state, &histogram, MAX_PRECISE_SCORE).0, 1);
+        assert_eq!(passing_and_tier_counts(&state, &histogram, MAX_PRECISE_SCORE + 1).0, 0);
+        assert_eq!(passing_and_tier_counts(&state, &histogram, 0).1, vec![1, 2, 2, 1]);
+    }
+
+    #[test]
+    fn probabilities_without_known_entries() {
+        assert_eq!(probability_bps(0, 0), 0);
+        assert_eq!(probability_bps(5, 0), 0);
+        assert_eq!(tier_pass_probabilities(&[0, 0, 0, 0], 0), vec![0, 0, 0, 0]);
+        
+        // A map of only unknown scores has no known entries to estimate from
+        let state = percent_state(&[UNKNOWN_SCORE; 4]);
+        let known_entries: u32 = state.score_histogram.iter().sum();
+        assert_eq!(known_entries, 0);
+        let (passing, tier_counts) = passing_and_tier_counts(&state, &state.score_histogram, 50);
+        assert_eq!(probability_bps(passing, known_entries), 0);
+        assert_eq!(tier_pass_probabilities(&tier_counts, known_entries), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn probabilities_from_known_entries() {
+        assert_eq!(probability_bps(1, 3), 3_333);
+        assert_eq!(probability_bps(3, 3), 10_000);
+        assert_eq!(tier_pass_probabilities(&[1, 2, 1, 0], 4), vec![10_000, 7_500, 2_500, 0]);
+    }
+}
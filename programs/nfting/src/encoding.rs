@@ -0,0 +1,231 @@
+use anchor_lang::prelude::*;
+
+use crate::state::RarityEncoding;
+use crate::ErrorCode;
+
+// Largest palette of a tier-packed payload, one entry per 4-bit value
+pub const MAX_PALETTE_SIZE: usize = 16;
+
+fn invalid_payload() -> Error {
+    msg!("Malformed rarity payload");
+    error!(ErrorCode::InvalidRarityPayload)
+}
+
+// Read an unsigned LEB128 varint
+fn read_varint(payload: &[u8], offset: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *payload.get(*offset).ok_or_else(invalid_payload)?;
+        *offset += 1;
+        if shift > 63 {
+            return Err(invalid_payload());
+        }
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_score(payload: &[u8], offset: &mut usize) -> Result<u16> {
+    u16::try_from(read_varint(payload, offset)?).map_err(|_| invalid_payload())
+}
+
+// Decode a rarity payload, passing each score to `write` in index order.
+// `width` is the state's bytes per score, used by the raw encoding.
+pub fn decode_scores(
+    encoding: RarityEncoding,
+    width: usize,
+    payload: &[u8],
+    mut write: impl FnMut(u16) -> Result<()>,
+) -> Result<()> {
+    let mut offset = 0;
+    match encoding {
+        RarityEncoding::Raw => {
+            if payload.len() % width != 0 {
+                msg!("Rarity data length {} is not a multiple of the {}-byte score width", payload.len(), width);
+                return Err(invalid_payload());
+            }
+            for bytes in payload.chunks_exact(width) {
+                write(if width == 2 { u16::from_le_bytes([bytes[0], bytes[1]]) } else { bytes[0] as u16 })?;
+            }
+        }
+        RarityEncoding::RunLength => {
+            while offset < payload.len() {
+                let run = read_varint(payload, &mut offset)?;
+                let score = read_score(payload, &mut offset)?;
+                if run == 0 {
+                    return Err(invalid_payload());
+                }
+                for _ in 0..run {
+                    write(score)?;
+                }
+            }
+        }
+        RarityEncoding::TierPacked => {
+            let count = read_varint(payload, &mut offset)?;
+            let palette_size = *payload.get(offset).ok_or_else(invalid_payload)? as usize;
+            offset += 1;
+            if palette_size == 0 || palette_size > MAX_PALETTE_SIZE {
+                return Err(invalid_payload());
+            }
+            let mut palette = [0u16; MAX_PALETTE_SIZE];
+            for entry in palette.iter_mut().take(palette_size) {
+                *entry = read_score(payload, &mut offset)?;
+            }
+            
+            // Two scores per byte, low nibble first
+            let packed = &payload[offset..];
+            if (packed.len() as u64) != count.div_ceil(2) {
+                return Err(invalid_payload());
+            }
+            for i in 0..count as usize {
+                let position = (packed[i / 2] >> (4 * (i % 2))) & 0x0f;
+                if position as usize >= palette_size {
+                    return Err(invalid_payload());
+                }
+                write(palette[position as usize])?;
+            }
+        }
+        RarityEncoding::DeltaVarint => {
+            let mut score = 0i64;
+            while offset < payload.len() {
+                let zigzag = read_varint(payload, &mut offset)?;
+                let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+                score = score.checked_add(delta).ok_or_else(invalid_payload)?;
+                write(u16::try_from(score).map_err(|_| invalid_payload())?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(encoding: RarityEncoding, width: usize, payload: &[u8]) -> Result<Vec<u16>> {
+        let mut scores = Vec::new();
+        decode_scores(encoding, width, payload, |score| {
+            scores.push(score);
+            Ok(())
+        })?;
+        Ok(scores)
+    }
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn zigzag(delta: i64) -> u64 {
+        ((delta << 1) ^ (delta >> 63)) as u64
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        assert_eq!(decode(RarityEncoding::Raw, 1, &[0, 42, 100, 0xff]).unwrap(), vec![0, 42, 100, 0xff]);
+        
+        let scores = [0u16, 4_250, 10_000, 0xffff];
+        let payload: Vec<u8> = scores.iter().flat_map(|score| score.to_le_bytes()).collect();
+        assert_eq!(decode(RarityEncoding::Raw, 2, &payload).unwrap(), scores);
+        assert!(decode(RarityEncoding::Raw, 2, &payload[..3]).is_err());
+    }
+
+    #[test]
+    fn run_length_round_trip() {
+        let mut payload = Vec::new();
+        for (run, score) in [(3u64, 50u64), (200, 10_000), (1, 0xffff)] {
+            varint(run, &mut payload);
+            varint(score, &mut payload);
+        }
+        let mut expected = vec![50; 3];
+        expected.extend(vec![10_000; 200]);
+        expected.push(0xffff);
+        assert_eq!(decode(RarityEncoding::RunLength, 2, &payload).unwrap(), expected);
+    }
+
+    #[test]
+    fn tier_packed_round_trip() {
+        let palette = [10u64, 90, 255];
+        let scores = [0usize, 1, 2, 1, 0];
+        let mut payload = Vec::new();
+        varint(scores.len() as u64, &mut payload);
+        payload.push(palette.len() as u8);
+        for entry in palette {
+            varint(entry, &mut payload);
+        }
+        for pair in scores.chunks(2) {
+            payload.push(pair[0] as u8 | (pair.get(1).copied().unwrap_or(0) as u8) << 4);
+        }
+        let expected: Vec<u16> = scores.iter().map(|&position| palette[position] as u16).collect();
+        assert_eq!(decode(RarityEncoding::TierPacked, 1, &payload).unwrap(), expected);
+    }
+
+    #[test]
+    fn delta_varint_round_trip() {
+        let scores = [5_000i64, 5_010, 4_990, 0, 10_000, 10_000];
+        let mut payload = Vec::new();
+        let mut previous = 0;
+        for score in scores {
+            varint(zigzag(score - previous), &mut payload);
+            previous = score;
+        }
+        let expected: Vec<u16> = scores.iter().map(|&score| score as u16).collect();
+        assert_eq!(decode(RarityEncoding::DeltaVarint, 2, &payload).unwrap(), expected);
+    }
+
+    #[test]
+    fn truncated_varint() {
+        assert!(decode(RarityEncoding::RunLength, 1, &[0x85]).is_err());
+        assert!(decode(RarityEncoding::RunLength, 1, &[2]).is_err());
+        assert!(decode(RarityEncoding::DeltaVarint, 1, &[0x80, 0x80]).is_err());
+        assert!(decode(RarityEncoding::DeltaVarint, 1, &[0xff; 11]).is_err());
+    }
+
+    #[test]
+    fn zero_length_run() {
+        assert!(decode(RarityEncoding::RunLength, 1, &[0, 50]).is_err());
+    }
+
+    #[test]
+    fn palette_size_out_of_range() {
+        assert!(decode(RarityEncoding::TierPacked, 1, &[1, 0, 0]).is_err());
+        
+        let mut payload = vec![1, (MAX_PALETTE_SIZE + 1) as u8];
+        payload.extend(0..=MAX_PALETTE_SIZE as u8);
+        payload.push(0);
+        assert!(decode(RarityEncoding::TierPacked, 1, &payload).is_err());
+    }
+
+    #[test]
+    fn nibble_beyond_palette() {
+        // Two palette entries, second score points at the third
+        assert!(decode(RarityEncoding::TierPacked, 1, &[2, 2, 10, 90, 0x21]).is_err());
+        assert_eq!(decode(RarityEncoding::TierPacked, 1, &[2, 2, 10, 90, 0x01]).unwrap(), vec![90, 10]);
+    }
+
+    #[test]
+    fn packed_length_mismatch() {
+        assert!(decode(RarityEncoding::TierPacked, 1, &[3, 1, 10, 0x00]).is_err());
+        assert!(decode(RarityEncoding::TierPacked, 1, &[2, 1, 10, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn delta_out_of_range() {
+        let mut underflow = Vec::new();
+        varint(zigzag(5), &mut underflow);
+        varint(zigzag(-6), &mut underflow);
+        assert!(decode(RarityEncoding::DeltaVarint, 2, &underflow).is_err());
+        
+        let mut overflow = Vec::new();
+        varint(zigzag(0xffff), &mut overflow);
+        varint(zigzag(1), &mut overflow);
+        assert!(decode(RarityEncoding::DeltaVarint, 2, &overflow).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use anchor_lang::prelude::*;
+
+use crate::results::MintStatistics;
+use crate::state::RankRequirement;
+
+// Emitted when a rarity state is created for a merkle tree
+#[event]
+pub struct StateInitialized {
+    pub state: Pubkey,
+
+    pub authority: Pubkey,
+
+    pub merkle_tree: Pubkey,
+
+    pub rarity_thresholds: Vec<u8>,
+}
+
+// Emitted when a range of the rarity map is written
+#[event]
+pub struct RarityDataUpdated {
+    pub state: Pubkey,
+
+    // First index written
+    pub start_index: u64,
+
+    // One past the last index written
+    pub end_index: u64,
+
+    // Keccak hash of the uploaded rarity data, to check an upload against the source file
+    pub data_hash: [u8; 32],
+}
+
+// Emitted when scores at scattered indices are written by update_rarity_entries
+#[event]
+pub struct RarityEntriesUpdated {
+    pub state: Pubkey,
+
+    pub entry_count: u32,
+
+    // Keccak hash of the Borsh-encoded entries
+    pub data_hash: [u8; 32],
+}
+
+// Emitted when a range of the rarity map is reset to unknown
+#[event]
+pub struct RarityRangeCleared {
+    pub state: Pubkey,
+
+    pub start_index: u64,
+
+    pub end_index: u64,
+}
+
+// Emitted when the URI template, name template or index source of a state changes
+#[event]
+pub struct ConfigUpdated {
+    pub state: Pubkey,
+}
+
+// Emitted when trait frequencies are uploaded to a trait table
+#[event]
+pub struct TraitTableUpdated {
+    pub trait_table: Pubkey,
+
+    // Number of distinct trait values now stored
+    pub trait_count: u32,
+}
+
+// Emitted when a launchpad adapter is registered or updated
+#[event]
+pub struct LaunchpadAdapterUpdated {
+    pub program_id: Pubkey,
+
+    // Number of recognized mint instructions
+    pub instruction_count: u32,
+}
+
+// Emitted when an NFT meets the requested rarity threshold
+#[event]
+pub struct ValidationPassed {
+    pub state: Pubkey,
+
+    // Asset ID or mint of the NFT, when known (default pubkey otherwise)
+    pub asset_id: Pubkey,
+
+    // NFT index the asset resolved to (None when scored from traits)
+    pub index: Option<u64>,
+
+    // Rarity score, in the state's score unit
+    pub score: u16,
+
+    pub threshold: u16,
+
+    pub minter: Pubkey,
+}
+
+// Emitted just before a validation fails, so the outcome shows up in the failed transaction's logs
+#[event]
+pub struct ValidationFailed {
+    pub state: Pubkey,
+
+    // Asset ID or mint of the NFT, when known (default pubkey otherwise)
+    pub asset_id: Pubkey,
+
+    // NFT index the asset resolved to (None when scored from traits or not resolved)
+    pub index: Option<u64>,
+
+    // Rarity score of the asset, in the state's score unit (0 when it could not be scored)
+    pub score: u16,
+
+    pub threshold: u16,
+
+    pub minter: Pubkey,
+
+    // Program error raised before scoring, if the NFT could not be scored at all
+    pub error_code: Option<u64>,
+}
+
+// Emitted by validation instructions run with dry_run set, in place of
+// failing the transaction or charging the fee
+#[event]
+pub struct ValidationPreviewed {
+    pub state: Pubkey,
+
+    // Asset ID or mint of the NFT, when known (default pubkey otherwise)
+    pub asset_id: Pubkey,
+
+    // NFT index the asset resolved to (None when scored from traits or not resolved)
+    pub index: Option<u64>,
+
+    // Rarity score of the asset, in the state's score unit (0 when it could not be scored)
+    pub score: u16,
+
+    // Rarity tier of the score
+    pub tier: u8,
+
+    // Minimum score that was requested
+    pub threshold: u16,
+
+    // Whether a real validation would have passed
+    pub passed: bool,
+
+    pub minter: Pubkey,
+
+    // Program error a real validation would have failed with before scoring, if any
+    pub error_code: Option<u64>,
+}
+
+// Emitted by validate_rank, including dry runs and just before a failed validation errors
+#[event]
+pub struct RankValidated {
+    pub state: Pubkey,
+
+    pub asset_id: Pubkey,
+
+    // NFT index the asset resolved to, if it could be resolved
+    pub index: Option<u64>,
+
+    // Rank of the NFT (0 when it could not be ranked)
+    pub rank: u32,
+
+    pub total_ranked: u32,
+
+    pub requirement: RankRequirement,
+
+    pub passed: bool,
+
+    pub dry_run: bool,
+
+    pub minter: Pubkey,
+
+    // Program error raised before ranking, if the NFT could not be ranked at all
+    pub error_code: Option<u64>,
+}
+
+// Emitted once per validate_batch call
+#[event]
+pub struct BatchValidated {
+    pub state: Pubkey,
+
+    pub item_count: u32,
+
+    pub passed_count: u32,
+
+    // Items that could not be scored (only in ReportEach mode)
+    pub unscored_count: u32,
+
+    pub threshold: u16,
+
+    pub minter: Pubkey,
+}
+
+// Emitted when record_mint appends a mint to the state's history
+#[event]
+pub struct MintRecorded {
+    pub state: Pubkey,
+
+    pub asset_id: Pubkey,
+
+    // NFT index the asset resolved to, if its name or URI matched the templates
+    pub index: Option<u64>,
+
+    // Rarity score at that index as a percentage, if known
+    pub score: Option<u8>,
+
+    pub minter: Pubkey,
+
+    pub timestamp: i64,
+}
+
+// Emitted by get_mint_statistics
+#[event]
+pub struct MintStatisticsReported {
+    pub state: Pubkey,
+
+    pub statistics: MintStatistics,
+}
+
+// Emitted whenever a fee is transferred to the fee receiver
+#[event]
+pub struct FeeCharged {
+    pub payer: Pubkey,
+
+    pub amount: u64,
+}
@@ -0,0 +1,251 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{RarityState, IndexSource, UriTemplate, NameTemplate};
+use crate::ErrorCode;
+
+// Extract the NFT index of an asset using the state's configured index source
+pub fn extract_index(state: &RarityState, name: &str, uri: &str) -> Result<u64> {
+    match state.index_source {
+        IndexSource::Uri => extract_index_from_uri(&state.uri_template, uri),
+        IndexSource::Name => extract_index_from_name(&state.name_template, name),
+    }
+}
+
+// Extract the NFT index from a metadata URI using the collection's URI template.
+// The URI must start with the configured base, so assets from other collections
+// whose URIs merely end in "<number>.json" are rejected.
+pub fn extract_index_from_uri(template: &UriTemplate, uri: &str) -> Result<u64> {
+    if template.base_uri.is_empty() {
+        msg!("No URI template configured for this collection");
+        return Err(error!(ErrorCode::UriTemplateNotConfigured));
+    }
+
+    // Metaplex programs pad strings with null bytes
+    let uri = uri.trim_end_matches('\0');
+
+    let remainder = match uri.strip_prefix(template.base_uri.as_str()) {
+        Some(remainder) => remainder,
+        None => {
+            msg!("URI {} does not start with collection base {}", uri, template.base_uri);
+            return Err(error!(ErrorCode::UriTemplateMismatch));
+        }
+    };
+
+    let remainder = match remainder.strip_suffix(template.suffix.as_str()) {
+        Some(remainder) => remainder,
+        None => {
+            msg!("URI {} does not end with suffix {}", uri, template.suffix);
+            return Err(error!(ErrorCode::UriTemplateMismatch));
+        }
+    };
+
+    // The index is the last path segment, so "base/123/x.json" does not read as index 123
+    let mut segments = remainder.split('/');
+    let segment = match segments.nth(template.index_segment as usize) {
+        Some(segment) if segments.next().is_none() => segment,
+        _ => {
+            msg!("URI {} does not end with path segment {}", uri, template.index_segment);
+            return Err(error!(ErrorCode::UriTemplateMismatch));
+        }
+    };
+
+    match parse_index(template, segment) {
+        Some(index) => Ok(index),
+        None => {
+            msg!("Path segment {} is not a valid index", segment);
+            Err(error!(ErrorCode::UriTemplateMismatch))
+        }
+    }
+}
+
+// Extract the NFT index from an asset name of the form "<prefix>#<n>"
+pub fn extract_index_from_name(template: &NameTemplate, name: &str) -> Result<u64> {
+    // Metaplex programs pad strings with null bytes
+    let name = name.trim_end_matches('\0');
+
+    let number = match name
+        .strip_prefix(template.prefix.as_str())
+        .and_then(|rest| rest.strip_prefix('#'))
+    {
+        Some(number) => number,
+        None => {
+            msg!("Name {} does not match \"{}#<n>\"", name, template.prefix);
+            return Err(error!(ErrorCode::NameTemplateMismatch));
+        }
+    };
+
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        msg!("Name {} does not end in a number", name);
+        return Err(error!(ErrorCode::NameTemplateMismatch));
+    }
+
+    let number = match number.parse::<u64>() {
+        Ok(number) => number,
+        Err(_) => return Err(error!(ErrorCode::NameTemplateMismatch)),
+    };
+
+    match number.checked_sub(template.index_offset as u64) {
+        Some(index) => Ok(index),
+        None => {
+            msg!("Number {} is below the index offset {}", number, template.index_offset);
+            Err(error!(ErrorCode::NameTemplateMismatch))
+        }
+    }
+}
+
+// Build the URI an index maps to, when the index is the first segment after the base
+pub fn format_uri(template: &UriTemplate, index: u64) -> Option<String> {
+    if template.base_uri.is_empty() || template.index_segment != 0 {
+        return None;
+    }
+
+    let width = template.zero_pad as usize;
+    let index_str = if template.hex_index {
+        format!("{:0width$x}", index, width = width)
+    } else {
+        format!("{:0width$}", index, width = width)
+    };
+
+    Some(format!("{}{}{}", template.base_uri, index_str, template.suffix))
+}
+
+// Parse a single path segment as an index, enforcing the template's encoding
+fn parse_index(template: &UriTemplate, segment: &str) -> Option<u64> {
+    if segment.is_empty() {
+        return None;
+    }
+
+    // Reject signs, whitespace and anything else `parse` would tolerate
+    let valid_digits = if template.hex_index {
+        segment.chars().all(|c| c.is_ascii_hexdigit())
+    } else {
+        segment.chars().all(|c| c.is_ascii_digit())
+    };
+    if !valid_digits {
+        return None;
+    }
+
+    if template.zero_pad > 0 {
+        // Padded indices must have exactly the configured width
+        if segment.len() != template.zero_pad as usize {
+            return None;
+        }
+    } else if segment.len() > 1 && segment.starts_with('0') {
+        // Unpadded indices never carry leading zeros
+        return None;
+    }
+
+    if template.hex_index {
+        u64::from_str_radix(segment, 16).ok()
+    } else {
+        segment.parse::<u64>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri_template(base_uri: &str, index_segment: u8, suffix: &str, zero_pad: u8, hex_index: bool) -> UriTemplate {
+        UriTemplate {
+            base_uri: base_uri.to_string(),
+            index_segment,
+            suffix: suffix.to_string(),
+            zero_pad,
+            hex_index,
+        }
+    }
+
+    fn name_template(prefix: &str, index_offset: u32) -> NameTemplate {
+        NameTemplate {
+            prefix: prefix.to_string(),
+            index_offset,
+        }
+    }
+
+    #[test]
+    fn uri_index_after_base() {
+        let template = uri_template("https://example.com/nft/", 0, ".json", 0, false);
+        assert_eq!(extract_index_from_uri(&template, "https://example.com/nft/123.json").unwrap(), 123);
+        assert_eq!(extract_index_from_uri(&template, "https://example.com/nft/0.json").unwrap(), 0);
+        assert_eq!(extract_index_from_uri(&template, "https://example.com/nft/7.json\0\0\0").unwrap(), 7);
+    }
+
+    #[test]
+    fn uri_index_in_later_segment() {
+        let template = uri_template("https://example.com/", 1, "/metadata.json", 0, false);
+        assert_eq!(extract_index_from_uri(&template, "https://example.com/items/42/metadata.json").unwrap(), 42);
+    }
+
+    #[test]
+    fn uri_index_must_be_last_segment() {
+        let template = uri_template("https://example.com/nft/", 0, ".json", 0, false);
+        assert!(extract_index_from_uri(&template, "https://example.com/nft/123/x.json").is_err());
+
+        let template = uri_template("https://example.com/", 0, "", 0, false);
+        assert!(extract_index_from_uri(&template, "https://example.com/123/456").is_err());
+    }
+
+    #[test]
+    fn uri_zero_padding() {
+        let template = uri_template("ipfs://cid/", 0, ".json", 4, false);
+        assert_eq!(extract_index_from_uri(&template, "ipfs://cid/0042.json").unwrap(), 42);
+        assert!(extract_index_from_uri(&template, "ipfs://cid/42.json").is_err());
+        assert!(extract_index_from_uri(&template, "ipfs://cid/00042.json").is_err());
+
+        let unpadded = uri_template("ipfs://cid/", 0, ".json", 0, false);
+        assert!(extract_index_from_uri(&unpadded, "ipfs://cid/042.json").is_err());
+    }
+
+    #[test]
+    fn uri_hex_index() {
+        let template = uri_template("ipfs://cid/", 0, ".json", 0, true);
+        assert_eq!(extract_index_from_uri(&template, "ipfs://cid/ff.json").unwrap(), 255);
+        assert!(extract_index_from_uri(&template, "ipfs://cid/fg.json").is_err());
+
+        let decimal = uri_template("ipfs://cid/", 0, ".json", 0, false);
+        assert!(extract_index_from_uri(&decimal, "ipfs://cid/ff.json").is_err());
+    }
+
+    #[test]
+    fn uri_mismatches() {
+        let template = uri_template("https://example.com/nft/", 0, ".json", 0, false);
+        assert!(extract_index_from_uri(&template, "https://other.com/nft/1.json").is_err());
+        assert!(extract_index_from_uri(&template, "https://example.com/nft/1.png").is_err());
+        assert!(extract_index_from_uri(&template, "https://example.com/nft/.json").is_err());
+        assert!(extract_index_from_uri(&template, "https://example.com/nft/+1.json").is_err());
+        assert!(extract_index_from_uri(&template, "https://example.com/nft/ 1.json").is_err());
+
+        let unconfigured = uri_template("", 0, ".json", 0, false);
+        assert!(extract_index_from_uri(&unconfigured, "https://example.com/nft/1.json").is_err());
+    }
+
+    #[test]
+    fn name_index() {
+        let template = name_template("Crowned Character ", 0);
+        assert_eq!(extract_index_from_name(&template, "Crowned Character #123").unwrap(), 123);
+        assert_eq!(extract_index_from_name(&template, "Crowned Character #5\0\0").unwrap(), 5);
+
+        let offset = name_template("Crowned Character ", 1);
+        assert_eq!(extract_index_from_name(&offset, "Crowned Character #1").unwrap(), 0);
+        assert!(extract_index_from_name(&offset, "Crowned Character #0").is_err());
+    }
+
+    #[test]
+    fn name_mismatches() {
+        let template = name_template("Crowned Character ", 0);
+        assert!(extract_index_from_name(&template, "Other Character #1").is_err());
+        assert!(extract_index_from_name(&template, "Crowned Character 1").is_err());
+        assert!(extract_index_from_name(&template, "Crowned Character #").is_err());
+        assert!(extract_index_from_name(&template, "Crowned Character #1a").is_err());
+        assert!(extract_index_from_name(&template, "Crowned Character #-1").is_err());
+    }
+
+    #[test]
+    fn format_uri_round_trips() {
+        let template = uri_template("ipfs://cid/", 0, ".json", 4, true);
+        let uri = format_uri(&template, 0x2a).unwrap();
+        assert_eq!(uri, "ipfs://cid/002a.json");
+        assert_eq!(extract_index_from_uri(&template, &uri).unwrap(), 0x2a);
+    }
+}
@@ -6,15 +6,56 @@ use solana_program::{
     keccak,
     program_pack::Pack,
     instruction::Instruction,
+    entrypoint::MAX_PERMITTED_DATA_INCREASE,
     sysvar::{instructions::{load_instruction_at_checked, get_instruction_relative}, SysvarId},
+    program::get_return_data,
 };
 // Import the TreeConfig directly from Bubblegum program
 use spl_account_compression::{self, program::SplAccountCompression};
 use mpl_core::accounts::BaseAssetV1;
+use mpl_core::types::{Attributes, PluginType, UpdateAuthority};
+use mpl_bubblegum::types::MetadataArgs;
+use mpl_token_metadata::accounts::Metadata;
 
-use crate::state::{RarityState, MintRecord, MintPattern};
+use crate::state::{
+    RarityState, MintRecord, UriTemplate, IndexSource, NameTemplate,
+    TraitTable, TraitFrequency, TRAIT_TABLE_SIZE, MINT_PATTERN_SIZE, MAX_MINT_PATTERNS,
+    MintHistory, MINT_HISTORY_SIZE, MAX_MINT_HISTORY_CAPACITY, MintRecordAccount, MINT_RECORD_ACCOUNT_SIZE,
+    MinterStats, MINTER_STATS_SIZE, MINTER_RANK_SIZE, MAX_TOP_MINTERS, SCORE_HISTOGRAM_BUCKETS,
+    RankTable, RankRequirement, RANK_TABLE_SIZE, MAX_PRECISE_SCORE,
+    LaunchpadAdapter, AdapterInstruction, IndexLocation, BatchItems, BatchMode, RarityEntry, RarityEncoding,
+};
+use crate::extraction::{extract_index, format_uri};
+use crate::traits::statistical_rarity_score;
+use crate::candy_machine::{load_candy_machine, predict_next_config_line};
+use crate::token_2022::load_token_metadata;
+use crate::patterns::{index_difference, record_difference};
+use crate::stats::{find_minter_stats, record_fee, record_validation, update_leaderboard, update_minter_stats};
+use crate::distribution::{
+    score_histogram, ensure_histogram, grow_rarity_map, write_score, passing_and_tier_counts, unknown_ranges,
+    probability_bps, tier_pass_probabilities, precise_tier_counts,
+};
+use crate::encoding::decode_scores;
+use crate::adapter::{validate_adapter_instructions, find_adapter_instruction, preceding_instructions, read_index_from_data};
+use crate::results::{RarityResult, IndexPrediction, BatchResult, MintStatistics, RarityDistribution, RarityCoverage, RankResult, rarity_tier};
+use crate::events::{
+    StateInitialized, RarityDataUpdated, ConfigUpdated, TraitTableUpdated, LaunchpadAdapterUpdated,
+    ValidationPassed, ValidationFailed, ValidationPreviewed, BatchValidated, FeeCharged, MintRecorded,
+    MintStatisticsReported, RankValidated, RarityEntriesUpdated, RarityRangeCleared,
+};
 
 mod state;
+mod extraction;
+mod traits;
+mod candy_machine;
+mod token_2022;
+mod adapter;
+mod patterns;
+mod stats;
+mod distribution;
+mod encoding;
+pub mod results;
+pub mod events;
 
 mod bubblegum_program {
     use anchor_lang::prelude::*;
@@ -30,6 +71,11 @@ mod bubblegum_program {
     
     // Bubblegum instruction discriminator for mint_to_collection_v1
     pub const MINT_TO_COLLECTION_V1_DISCRIMINATOR: [u8; 8] = [245, 201, 109, 234, 21, 117, 186, 159];
+    
+    // Positions of the leaf owner, merkle tree and payer in the mint_v1 and mint_to_collection_v1 account lists
+    pub const LEAF_OWNER_ACCOUNT_INDEX: usize = 1;
+    pub const MERKLE_TREE_ACCOUNT_INDEX: usize = 3;
+    pub const PAYER_ACCOUNT_INDEX: usize = 4;
 }
 
 mod menagerie_program {
@@ -51,6 +97,16 @@ mod menagerie_program {
     pub const MINT_CV3_DISCRIMINATOR: [u8; 8] = [0x38, 0xa6, 0x52, 0x4f, 0xe8, 0x00, 0xf6, 0x11];
 }
 
+mod candy_machine_program {
+    use anchor_lang::prelude::*;
+    use std::str::FromStr;
+    
+    // Candy Machine Core (v3) program
+    pub fn id() -> Pubkey {
+        Pubkey::from_str("CndyV3LdqHUfDLmE5naZjVN8rBZz4tqhdefbAnjHG3JR").unwrap()
+    }
+}
+
 mod fee_receiver {
     use anchor_lang::prelude::*;
     use std::str::FromStr;
@@ -81,15 +137,24 @@ mod mpl_core_program {
     
     // CreateV1 instruction discriminator
     pub const CREATE_V1_DISCRIMINATOR: u8 = 0;
+    
+    // CreateV2 instruction discriminator
+    pub const CREATE_V2_DISCRIMINATOR: u8 = 20;
+    
+    // Positions of the asset and payer in the CreateV1/CreateV2 account lists
+    pub const ASSET_ACCOUNT_INDEX: usize = 0;
+    pub const PAYER_ACCOUNT_INDEX: usize = 3;
 }
 
 declare_id!("14m2HBX8Y3FVNwdxGLhnDBHhsHG9QhjNfP7thXqm8iRb");
 
 const FEE_LAMPORTS: u64 = 100_000_000; // 0.1 SOL
+const MAX_BATCH_SIZE: usize = 64;
+const MAX_REPORTED_PATTERNS: usize = 5;
+const MAX_REPORTED_RANGES: usize = 64;
+const MAX_CLEAR_RANGE: u64 = 2048;
 const ASSET_PREFIX: &[u8] = b"asset";
 const METADATA_PREFIX: &[u8] = b"metadata";
-const METADATA_URI_OFFSET: usize = 98; // Approximate offset for URI in metadata account
-const IPFS_BASE_URI: &str = "https://gateway.pinit.io/ipfs/Qmd2mt5hpF9d9QMDhpX9SecoPsvdpqcGVnP7ETfxB6hrr3/";
 
 #[program]
 pub mod nfting {
@@ -102,22 +167,12 @@ pub mod nfting {
         ctx: Context<Initialize>,
         rarity_thresholds: Vec<u8>,
     ) -> Result<()> {
-        msg!("Processing fee transfer of {} lamports", FEE_LAMPORTS);
-        // Transfer fee
-        let transfer_ix = system_instruction::transfer(
-            &ctx.accounts.authority.key(),
-            &ctx.accounts.fee_receiver.key(),
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
             FEE_LAMPORTS,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.fee_receiver.to_account_info(),
-                ctx.accounts.authority.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
         )?;
-        msg!("Fee transfer successful");
 
         // Get max depth and max buffer size from the merkle tree
         let merkle_tree_account = ctx.accounts.merkle_tree_account.to_account_info();
@@ -125,6 +180,7 @@ pub mod nfting {
 
         let state = &mut ctx.accounts.state;
         state.authority = ctx.accounts.authority.key();
+        state.merkle_tree = ctx.accounts.merkle_tree.key();
         state.rarity_thresholds = rarity_thresholds;
         state.bump = ctx.bumps.state;
         
@@ -133,508 +189,2359 @@ pub mod nfting {
         state.mint_records = Vec::new();
         state.mint_patterns = Vec::new();
         
+        emit!(StateInitialized {
+            state: state.key(),
+            authority: state.authority,
+            merkle_tree: state.merkle_tree,
+            rarity_thresholds: state.rarity_thresholds.clone(),
+        });
         Ok(())
     }
 
     /// Add or update rarity data for mint indices by directly fetching from the IPFS gateway
     pub fn update_rarity_data(
-        ctx: Context<UpdateRarityData>,
+        ctx: Context<UpdateRarityMap>,
         start_index: u64,
         rarity_data: Vec<u8>,
     ) -> Result<()> {
-        msg!("Processing fee transfer of {} lamports", FEE_LAMPORTS);
-        // Transfer fee
-        let transfer_ix = system_instruction::transfer(
-            &ctx.accounts.authority.key(),
-            &ctx.accounts.fee_receiver.key(),
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
             FEE_LAMPORTS,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.fee_receiver.to_account_info(),
-                ctx.accounts.authority.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
         )?;
-        msg!("Fee transfer successful");
 
-        let state = &mut ctx.accounts.state;
-        
-        // Make sure we stay within bounds
-        let end_index = start_index + rarity_data.len() as u64;
-        if end_index > u16::MAX as u64 {
-            return Err(error!(ErrorCode::IndexOutOfBounds));
-        }
-        
-        // Extend rarity_map if needed
-        if end_index > state.rarity_map.len() as u64 {
-            state.rarity_map.resize(end_index as usize, 0);
-        }
-        
-        // Update the rarity map with new data
-        for (i, rarity) in rarity_data.iter().enumerate() {
-            let index = (start_index as usize) + i;
-            state.rarity_map[index] = *rarity;
-        }
+        // u16 scores are uploaded as two little-endian bytes each
+        let end_index = write_encoded_scores(ctx.accounts, start_index, RarityEncoding::Raw, &rarity_data)?;
+        let state = &ctx.accounts.state;
         
+        emit!(RarityDataUpdated {
+            state: state.key(),
+            start_index,
+            end_index,
+            data_hash: keccak::hash(&rarity_data).to_bytes(),
+        });
         Ok(())
     }
 
-    /// Predict the next Bubblegum mint index and validate that it meets the rarity threshold
-    pub fn validate_mint(
-        ctx: Context<ValidateMint>,
-        min_rarity_percentage: u8,
-        num_minted: u64,
+    /// Add or update rarity data from a run-length, tier-packed or delta-varint encoded payload,
+    /// decoded on chain into the rarity map starting at `start_index`
+    pub fn update_rarity_data_encoded(
+        ctx: Context<UpdateRarityMap>,
+        start_index: u64,
+        encoding: RarityEncoding,
+        payload: Vec<u8>,
     ) -> Result<()> {
-        msg!("Starting validate_mint with min_rarity_percentage: {}", min_rarity_percentage);
-        msg!("Processing fee transfer of {} lamports", FEE_LAMPORTS);
-        // Transfer fee
-        let transfer_ix = system_instruction::transfer(
-            &ctx.accounts.minter.key(),
-            &ctx.accounts.fee_receiver.key(),
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
             FEE_LAMPORTS,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.fee_receiver.to_account_info(),
-                ctx.accounts.minter.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
         )?;
-        msg!("Fee transfer successful");
 
+        let end_index = write_encoded_scores(ctx.accounts, start_index, encoding, &payload)?;
         let state = &ctx.accounts.state;
-        msg!("Loaded state for merkle tree: {}", state.merkle_tree);
+        msg!("Decoded {} rarity scores from {} payload bytes", end_index - start_index, payload.len());
         
-            // Otherwise calculate the next mint index using the asset ID (prediction method)
-            // Calculate the next asset ID that will be minted (this matches Bubblegum's get_asset_id function)
-            let next_nonce = num_minted;
-            let next_asset_id = get_asset_id(&state.merkle_tree, next_nonce);
-            
-            msg!("Next asset ID will be: {}", next_asset_id);
-            
-            // Convert the asset ID bytes to a deterministic index for our rarity map
-            let asset_id_bytes = next_asset_id.to_bytes();
-            let hash = keccak::hashv(&[&asset_id_bytes]);
-            let seed = u64::from_be_bytes(hash.to_bytes()[0..8].try_into().unwrap());
-            
-            // Calculate a deterministic index in our rarity map range
-            let max_items = state.rarity_map.len() as u64;
-            if max_items == 0 {
-                return Err(error!(ErrorCode::NoRarityData));
+        emit!(RarityDataUpdated {
+            state: state.key(),
+            start_index,
+            end_index,
+            data_hash: keccak::hash(&payload).to_bytes(),
+        });
+        Ok(())
+    }
+
+    /// Write scores at scattered indices in one call, growing the rarity map as needed
+    /// (by at most 10 KB per call, as for any account)
+    pub fn update_rarity_entries(
+        ctx: Context<UpdateRarityMap>,
+        entries: Vec<RarityEntry>,
+    ) -> Result<()> {
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
+            FEE_LAMPORTS,
+        )?;
+        
+        let state = &ctx.accounts.state;
+        for entry in entries.iter() {
+            check_score(state, entry.score)?;
+            if entry.index >= u16::MAX as u32 {
+                return Err(error!(ErrorCode::IndexOutOfBounds));
             }
-            
-            let mint_index = seed % max_items;
-            msg!("Calculated mint index: {}", mint_index);
-            let actual_mint_index = mint_index as usize;
+        }
+        
+        // Size the account for the largest index before writing
+        let end_index = entries.iter().map(|entry| entry.index as usize + 1).max().unwrap_or(0);
+        let state_info = state.to_account_info();
+        grow_program_account(
+            &state_info,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            state.space_for_map(state_info.data_len(), end_index),
+        )?;
         
-        // Get and validate rarity
-        let rarity = state.rarity_map[actual_mint_index];
-        msg!("NFT at index {} has rarity score: {}", actual_mint_index, rarity);
+        let state = &mut ctx.accounts.state;
+        ensure_histogram(state);
+        grow_rarity_map(state, end_index);
         
-        if rarity < min_rarity_percentage {
-            msg!("Rarity {} below threshold {}", rarity, min_rarity_percentage);
-            return Err(error!(ErrorCode::RarityBelowThreshold));
+        for entry in entries.iter() {
+            write_score(state, entry.index as usize, entry.score);
         }
         
-        msg!("Validation successful: NFT meets rarity threshold");
+        msg!("Updated {} rarity entries", entries.len());
+        emit!(RarityEntriesUpdated {
+            state: state.key(),
+            entry_count: entries.len() as u32,
+            data_hash: keccak::hash(&entries.try_to_vec()?).to_bytes(),
+        });
         Ok(())
     }
-    
-    
-    /// Get statistics about mint patterns and rarity score distribution
-    pub fn get_mint_statistics(ctx: Context<GetMintStatistics>) -> Result<()> {
-        let state = &ctx.accounts.state;
-        
-        msg!("=== Mint Statistics ===");
-        msg!("Total mints analyzed: {}", state.total_mints);
+
+    /// Mark the scores of `start_index..end_index` as unknown, so validations of those indices fail
+    /// until they are uploaded again. At most 2048 indices are cleared per call.
+    pub fn clear_range(
+        ctx: Context<UpdateRarityMap>,
+        start_index: u64,
+        end_index: u64,
+    ) -> Result<()> {
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
+            FEE_LAMPORTS,
+        )?;
         
-        // Calculate total records with rarity scores
-        let records_with_rarity = state.mint_records.iter()
-            .filter(|r| r.rarity_score.is_some())
-            .count();
+        let state = &ctx.accounts.state;
+        if start_index >= end_index || end_index > state.map_len() as u64 {
+            msg!("Range {}..{} is outside the rarity map of length {}", start_index, end_index, state.map_len());
+            return Err(error!(ErrorCode::IndexOutOfBounds));
+        }
+        if end_index - start_index > MAX_CLEAR_RANGE {
+            msg!("At most {} indices can be cleared per call", MAX_CLEAR_RANGE);
+            return Err(error!(ErrorCode::IndexOutOfBounds));
+        }
         
-        msg!("Records with rarity scores: {}", records_with_rarity);
+        // Building the histogram of an older state needs room for it
+        let state_info = state.to_account_info();
+        grow_program_account(
+            &state_info,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            state.space_for_map(state_info.data_len(), 0),
+        )?;
         
-        // Report on rarity tiers
-        if !state.rarity_thresholds.is_empty() {
-            msg!("Rarity tier distribution:");
-            
-            // Count NFTs in each tier
-            let mut tier_counts = vec![0; state.rarity_thresholds.len() + 1];
-            
-            for record in state.mint_records.iter() {
-                if let Some(score) = record.rarity_score {
-                    let mut tier_index = 0;
-                    for (i, &threshold) in state.rarity_thresholds.iter().enumerate() {
-                        if score >= threshold {
-                            tier_index = i + 1;
-                        }
-                    }
-                    tier_counts[tier_index] += 1;
-                }
-            }
-            
-            // Report tier distribution
-            for i in 0..tier_counts.len() {
-                let tier_name = if i == 0 {
-                    "Common".to_string()
-                } else if i == tier_counts.len() - 1 {
-                    "Legendary".to_string()
-                } else {
-                    format!("Tier {}", i)
-                };
-                
-                let threshold = if i == 0 {
-                    0
-                } else {
-                    state.rarity_thresholds[i - 1] as u32
-                };
-                
-                msg!("{} ({}+): {} NFTs", tier_name, threshold, tier_counts[i]);
-            }
+        let state = &mut ctx.accounts.state;
+        ensure_histogram(state);
+        let unknown = state.unknown_score();
+        for index in start_index..end_index {
+            write_score(state, index as usize, unknown);
         }
         
-        // Report on mint patterns
-        if !state.mint_patterns.is_empty() {
-            msg!("Mint pattern distribution:");
-            
-            // Calculate total pattern occurrences
-            let total_occurrences: u64 = state.mint_patterns.iter()
-                .map(|p| p.occurrences)
-                .sum();
-            
-            // Sort patterns by occurrences (descending)
-            let mut sorted_patterns = state.mint_patterns.clone();
-            sorted_patterns.sort_by(|a, b| b.occurrences.cmp(&a.occurrences));
-            
-            // Display top patterns
-            for pattern in sorted_patterns.iter().take(5) {
-                let probability = (pattern.occurrences as f64 / total_occurrences as f64) * 100.0;
-                msg!(
-                    "Difference of {}: {} occurrences (approx. {:.1}% probability)",
-                    pattern.difference,
-                    pattern.occurrences,
-                    probability
-                );
-            }
+        msg!("Cleared rarity scores {} to {}", start_index, end_index);
+        emit!(RarityRangeCleared {
+            state: state.key(),
+            start_index,
+            end_index,
+        });
+        Ok(())
+    }
+
+    /// Configure how NFT indices are extracted from this collection's metadata URIs
+    pub fn set_uri_template(
+        ctx: Context<UpdateConfig>,
+        uri_template: UriTemplate,
+    ) -> Result<()> {
+        if uri_template.base_uri.is_empty()
+            || uri_template.base_uri.len() > state::MAX_URI_LENGTH
+            || uri_template.suffix.len() > state::MAX_URI_SUFFIX_LENGTH
+        {
+            return Err(error!(ErrorCode::InvalidUriTemplate));
         }
         
-        // Report on most active minters
-        if !state.mint_records.is_empty() {
-            // Count mints by minter
-            let mut minter_counts: std::collections::HashMap<Pubkey, u64> = std::collections::HashMap::new();
-            
-            for record in state.mint_records.iter() {
-                *minter_counts.entry(record.minter).or_insert(0) += 1;
-            }
-            
-            // Convert to vec for sorting
-            let mut minters: Vec<(Pubkey, u64)> = minter_counts.into_iter().collect();
-            minters.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by count descending
-            
-            msg!("Top minters:");
-            for (minter, count) in minters.iter().take(3) {
-                msg!("{}: {} mints", minter, count);
-            }
-        }
+        msg!(
+            "Setting URI template: base {}, segment {}, suffix {}, zero pad {}, hex {}",
+            uri_template.base_uri,
+            uri_template.index_segment,
+            uri_template.suffix,
+            uri_template.zero_pad,
+            uri_template.hex_index
+        );
         
-        msg!("=== End of Statistics ===");
-        Ok(())
+        let state = &mut ctx.accounts.state;
+        state.uri_template = uri_template;
+        
+        emit!(ConfigUpdated { state: state.key() });
+        finish_config_update(ctx.accounts)
     }
 
-    /// Validate a MintCore instruction by extracting URI data and checking the rarity
-    pub fn validate_mint_core(
-        ctx: Context<ValidateMintCore>,
-        min_rarity_percentage: u8,
+    /// Configure how NFT indices are extracted from asset names ("<prefix>#<n>")
+    pub fn set_name_template(
+        ctx: Context<UpdateConfig>,
+        name_template: NameTemplate,
     ) -> Result<()> {
-        msg!("Starting validate_mint_core with min_rarity_percentage: {}", min_rarity_percentage);
+        if name_template.prefix.len() > state::MAX_NAME_LENGTH {
+            return Err(error!(ErrorCode::InvalidNameTemplate));
+        }
         
-        // Process fee transfer
-        msg!("Processing fee transfer of {} lamports", FEE_LAMPORTS);
-        let transfer_ix = system_instruction::transfer(
-            &ctx.accounts.minter.key(),
-            &ctx.accounts.fee_receiver.key(),
-            FEE_LAMPORTS,
+        msg!(
+            "Setting name template: prefix \"{}\", index offset {}",
+            name_template.prefix,
+            name_template.index_offset
         );
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.fee_receiver.to_account_info(),
-                ctx.accounts.minter.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
-        msg!("Fee transfer successful");
-        let base_asset = &BaseAssetV1::deserialize(&mut &ctx.accounts.asset_account.to_account_info().try_borrow_data()?[..])?;
-        let uri = &base_asset.uri;
-        msg!("URI: {}", uri);
-        
-        // Extract the index from the URI
-        // URI format is typically like: https://gateway.pinit.io/ipfs/Qmd2mt5hpF9d9QMDhpX9SecoPsvdpqcGVnP7ETfxB6hrr3/123.json
-        if let Some(uri_str) = uri.to_string().strip_suffix(".json") {
-            if let Some(index_str) = uri_str.split('/').last() {
-                if let Ok(index) = index_str.parse::<usize>() {
-                    msg!("Found index in URI: {}", index);
-                    
-                    // Validate the index is within bounds of our rarity map
-                    let state = &ctx.accounts.state;
-                    if index < state.rarity_map.len() {
-                        // Get the rarity score for this index
-                        let rarity = state.rarity_map[index];
-                        msg!("NFT at index {} has rarity score: {}", index, rarity);
-                        
-                        // Check against threshold
-                        if rarity < min_rarity_percentage {
-                            msg!("Rarity {} below threshold {}", rarity, min_rarity_percentage);
-                            return Err(error!(ErrorCode::RarityBelowThreshold));
-                        }
-                        
-                        msg!("Validation successful: NFT meets rarity threshold");
-                        return Ok(());
-                    } else {
-                        msg!("Index {} is out of bounds for rarity map of length {}", 
-                             index, state.rarity_map.len());
-                        return Err(error!(ErrorCode::IndexOutOfBounds));
-                    }
-                }
-            }
+        
+        let state = &mut ctx.accounts.state;
+        state.name_template = name_template;
+        
+        emit!(ConfigUpdated { state: state.key() });
+        finish_config_update(ctx.accounts)
+    }
+
+    /// Choose whether NFT indices are extracted from the URI or the asset name
+    pub fn set_index_source(
+        ctx: Context<UpdateConfig>,
+        index_source: IndexSource,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.index_source = index_source;
+        
+        match index_source {
+            IndexSource::Uri => msg!("Extracting NFT indices from URIs"),
+            IndexSource::Name => msg!("Extracting NFT indices from names"),
         }
         
-        msg!("Failed to find index in URI pattern");
-        Err(error!(ErrorCode::MintTransactionAnalysisFailed))
+        emit!(ConfigUpdated { state: state.key() });
+        finish_config_update(ctx.accounts)
     }
 
-    /// Debug instruction for analyzing Menagerie MintCore instructions
-    pub fn debug_mint_core_instruction(
-        ctx: Context<DebugMintInstruction>,
-        target_slot: Option<u64>,
+    /// Choose between u8 percentage scores (`score_width` 1, the default) and u16 basis-point
+    /// scores (`score_width` 2) with their own tier thresholds. The width can only change while
+    /// the rarity map is empty.
+    pub fn set_score_width(
+        ctx: Context<UpdateConfig>,
+        score_width: u8,
+        precise_thresholds: Vec<u16>,
     ) -> Result<()> {
-        msg!("Starting debug for MintCore instruction");
+        let state = &mut ctx.accounts.state;
+        if score_width != 1 && score_width != 2 {
+            msg!("Score width must be 1 or 2 bytes");
+            return Err(error!(ErrorCode::InvalidScoreWidth));
+        }
+        if score_width as usize != state.entry_width() && !state.rarity_map.is_empty() {
+            msg!("Score width cannot change once rarity data has been uploaded");
+            return Err(error!(ErrorCode::InvalidScoreWidth));
+        }
+        if precise_thresholds.iter().any(|threshold| *threshold > MAX_PRECISE_SCORE) {
+            msg!("Thresholds must be at most {} basis points", MAX_PRECISE_SCORE);
+            return Err(error!(ErrorCode::InvalidScore));
+        }
         
-        // Get instructions sysvar
-        let ix_sysvar = &ctx.accounts.instructions_sysvar;
+        state.score_width = score_width;
+        state.precise_thresholds = precise_thresholds;
+        if state.is_precise() {
+            state.precise_tier_counts = precise_tier_counts(state);
+        }
         
-        // Get current instruction
-        let current_ix = load_instruction_at_checked(0, ix_sysvar)?;
-        if current_ix.program_id != crate::id() {
-            msg!("Current instruction is not from our program");
-            return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+        msg!("Rarity scores are stored in {} byte(s) per index", score_width);
+        emit!(ConfigUpdated { state: state.key() });
+        finish_config_update(ctx.accounts)
+    }
+
+    /// Set the MPL Core collection whose assets record_mint accepts
+    pub fn set_core_collection(ctx: Context<UpdateConfig>, core_collection: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.core_collection = core_collection;
+        
+        msg!("Recording Core mints of collection {}", core_collection);
+        emit!(ConfigUpdated { state: state.key() });
+        finish_config_update(ctx.accounts)
+    }
+
+    /// Predict the next Bubblegum mint index and validate that it meets the rarity threshold
+    pub fn validate_mint(
+        ctx: Context<ValidateMint>,
+        min_score: u16,
+        num_minted: u64,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_mint with min_score: {}", min_score);
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
         }
+
+        let state = &ctx.accounts.state;
+        msg!("Loaded state for merkle tree: {}", state.merkle_tree);
         
-        // Try to find the Menagerie instruction
-        // First check the previous instruction
-        let prev_ix = load_instruction_at_checked(0, ix_sysvar)?;
+        // Calculate the next asset ID that will be minted and the index it maps to
+        let next_asset_id = get_asset_id(&state.merkle_tree, num_minted);
+        msg!("Asset ID at nonce {}: {}", num_minted, next_asset_id);
+        let scored = asset_mint_index(state, &next_asset_id)
+            .and_then(|mint_index| Ok((Some(mint_index), rarity_at(state, mint_index)?)));
+
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            next_asset_id,
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+    
+    
+    /// Validate many indices, nonces or Core assets (remaining accounts) at once for a single combined fee
+    pub fn validate_batch(
+        ctx: Context<ValidateBatch>,
+        min_score: u16,
+        items: BatchItems,
+        mode: BatchMode,
+        dry_run: bool,
+    ) -> Result<BatchResult> {
+        msg!("Starting validate_batch with min_score: {}", min_score);
         
-        msg!("Previous instruction program: {}", prev_ix.program_id);
+        // Core assets are the remaining accounts, apart from the minter's statistics when passed
+        let minter = ctx.accounts.minter.key();
+        let minter_stats = find_minter_stats(ctx.remaining_accounts, &ctx.accounts.state.key(), &minter);
+        let assets: Vec<&AccountInfo> = ctx
+            .remaining_accounts
+            .iter()
+            .filter(|account| Some(account.key()) != minter_stats.map(|stats| stats.key()))
+            .collect();
         
-        // If it's from Menagerie, analyze it
-        if prev_ix.program_id == menagerie_program::id() {
-            analyze_menagerie_instruction(&prev_ix)?;
-        } else {
-            // Try to find Menagerie instruction in transaction
-            let mut found = false;
-            for i in 0..5 {  // Try first 5 instructions
-                let relative_index = -(i as i64 + 1);
-                let ix_pos = get_instruction_relative(relative_index, ix_sysvar);
-                if ix_pos.is_err() {
-                    continue;
+        let item_count = match &items {
+            BatchItems::Indices(indices) => indices.len(),
+            BatchItems::Nonces(nonces) => nonces.len(),
+            BatchItems::Assets => assets.len(),
+        };
+        if item_count == 0 || item_count > MAX_BATCH_SIZE {
+            msg!("Batch must contain 1 to {} items", MAX_BATCH_SIZE);
+            return Err(error!(ErrorCode::InvalidBatchSize));
+        }
+        
+        // A batch costs a single validation fee, however many items it holds; dry runs are free
+        let fee = if dry_run { 0 } else { FEE_LAMPORTS };
+        if fee > 0 {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                fee,
+            )?;
+        }
+        
+        // Dry runs report every item instead of failing on the first miss
+        let mode = if dry_run { BatchMode::ReportEach } else { mode };
+        
+        let state = &ctx.accounts.state;
+        let mut outcomes = Vec::with_capacity(item_count);
+        let mut result = BatchResult {
+            passed_bitmap: vec![0; item_count.div_ceil(8)],
+            unscored_bitmap: vec![0; item_count.div_ceil(8)],
+            scores: vec![0; item_count],
+            passed_count: 0,
+            unscored_count: 0,
+        };
+        
+        for i in 0..item_count {
+            let scored = match &items {
+                BatchItems::Indices(indices) => rarity_at(state, indices[i]),
+                BatchItems::Nonces(nonces) => {
+                    predict_mint_index(state, nonces[i]).and_then(|(_, index)| rarity_at(state, index))
                 }
-                
-                let ix_result = ix_pos;
-                if let Ok(ix) = ix_result {
-                    if ix.program_id == menagerie_program::id() {
-                        analyze_menagerie_instruction(&ix)?;
-                        found = true;
-                        break;
-                    }
+                BatchItems::Assets => core_asset_rarity(state, assets[i]),
+            };
+            
+            let rarity = match scored {
+                Ok(rarity) => rarity,
+                Err(err) if mode == BatchMode::RequireAll => return Err(err),
+                Err(_) => {
+                    msg!("Item {} could not be scored", i);
+                    result.unscored_bitmap[i / 8] |= 1 << (i % 8);
+                    result.unscored_count += 1;
+                    continue;
                 }
-            }
+            };
+            result.scores[i] = rarity;
             
-            if !found {
-                msg!("No Menagerie instruction found in recent transaction history");
+            let passed = rarity >= min_score;
+            if passed {
+                result.passed_bitmap[i / 8] |= 1 << (i % 8);
+                result.passed_count += 1;
+            } else if mode == BatchMode::RequireAll {
+                msg!("Item {} rarity {} below threshold {}", i, rarity, min_score);
+                return Err(error!(ErrorCode::RarityBelowThreshold));
             }
+            
+            outcomes.push((passed, state.score_percent(rarity)));
         }
         
-        // Print out the IPFS URI we expect to use
-        let ipfs_hash = "QmeFBDa3FJQDCmUSCBmWQS3sH89GUvm8KhgCFuNTmV129H";
-        msg!("Expected IPFS base URL: https://gateway.pinit.io/ipfs/{}/", ipfs_hash);
+        if !dry_run {
+            update_minter_stats(minter_stats, |stats| {
+                record_fee(stats, fee);
+                for (passed, score) in outcomes {
+                    record_validation(stats, passed, score, 0);
+                }
+            })?;
+        }
         
-        Ok(())
+        msg!("{} of {} items meet rarity threshold {}", result.passed_count, item_count, min_score);
+        emit!(BatchValidated {
+            state: state.key(),
+            item_count: item_count as u32,
+            passed_count: result.passed_count,
+            unscored_count: result.unscored_count,
+            threshold: min_score,
+            minter,
+        });
+        Ok(result)
     }
 
-    /// Add an instruction to directly extract NFT index from MPL Core create instruction
-    /// by parsing the program data. This is useful when we can't reliably extract the
-    /// index from the instruction data.
-    pub fn extract_nft_index_from_logs(
-        ctx: Context<ValidateMintFromLogs>,
-        min_rarity_percentage: u8,
+    /// Read-only query for the rarity score of an index (no fee, suitable for simulation)
+    pub fn get_rarity(ctx: Context<QueryRarity>, index: u64) -> Result<u16> {
+        let state = &ctx.accounts.state;
+        let rarity = rarity_at(state, index)?;
+        msg!("NFT at index {} has rarity score: {}", index, rarity);
+        Ok(rarity)
+    }
+
+    /// Read-only query for the rarity tier of an index (no fee, suitable for simulation)
+    pub fn get_tier(ctx: Context<QueryRarity>, index: u64) -> Result<u8> {
+        let state = &ctx.accounts.state;
+        let rarity = rarity_at(state, index)?;
+        let tier = rarity_tier(&state.thresholds(), rarity);
+        msg!("NFT at index {} is in tier {}", index, tier);
+        Ok(tier)
+    }
+
+    /// Read-only query for the asset ID and index a Bubblegum nonce maps to (no fee)
+    pub fn predict_index(ctx: Context<QueryRarity>, nonce: u64) -> Result<IndexPrediction> {
+        let state = &ctx.accounts.state;
+        let (asset_id, index) = predict_mint_index(state, nonce)?;
+        let rarity = rarity_at(state, index)?;
+        msg!("Nonce {} mints asset {} at index {} with rarity score: {}", nonce, asset_id, index, rarity);
+        
+        Ok(IndexPrediction {
+            asset_id,
+            index,
+            score: rarity,
+            tier: rarity_tier(&state.thresholds(), rarity),
+        })
+    }
+
+    /// Read-only query for the score distribution of the rarity map and the chance that a mint
+    /// meets `min_score` (no fee, suitable for simulation)
+    pub fn get_rarity_distribution(
+        ctx: Context<QueryRarity>,
+        min_score: u16,
+    ) -> Result<RarityDistribution> {
+        let state = &ctx.accounts.state;
+        
+        // States whose rarity data has not been written since the histogram was added are counted on the fly
+        let histogram = if state.score_histogram.len() == SCORE_HISTOGRAM_BUCKETS {
+            state.score_histogram.clone()
+        } else {
+            score_histogram(state)
+        };
+        
+        // Unknown entries are not in the histogram, so chances are out of the scored entries only
+        let total = state.map_len() as u32;
+        let known = histogram.iter().sum();
+        let (passing, tier_counts) = passing_and_tier_counts(state, &histogram, min_score);
+        let pass_probability_bps = probability_bps(passing, known);
+        let tier_pass_probability_bps = tier_pass_probabilities(&tier_counts, known);
+        
+        msg!("{} of {} scored NFTs score at least {} ({} bps)", passing, known, min_score, pass_probability_bps);
+        
+        Ok(RarityDistribution {
+            total,
+            known,
+            tier_counts,
+            histogram,
+            threshold: min_score,
+            passing,
+            pass_probability_bps,
+            tier_pass_probability_bps,
+        })
+    }
+
+    /// Read-only query for the index ranges that still have no rarity score, starting at
+    /// `start_index` (no fee, suitable for simulation)
+    pub fn get_rarity_coverage(ctx: Context<QueryRarity>, start_index: u32) -> Result<RarityCoverage> {
+        let state = &ctx.accounts.state;
+        let unknown = state.unknown_score();
+        
+        let total = state.map_len() as u32;
+        let known = state.scores().filter(|score| *score != unknown).count() as u32;
+        let (missing_ranges, next_index) = unknown_ranges(state, start_index as usize, MAX_REPORTED_RANGES);
+        
+        msg!("{} of {} NFTs have a rarity score", known, total);
+        for range in missing_ranges.iter() {
+            msg!("Missing scores for indices {} to {}", range.start_index, range.end_index - 1);
+        }
+        
+        Ok(RarityCoverage {
+            total,
+            known,
+            missing_ranges,
+            next_index,
+        })
+    }
+
+    /// Create the rank table of a rarity state, storing ranks as u16 (`rank_width` 2) or u32 (`rank_width` 4)
+    pub fn initialize_rank_table(
+        ctx: Context<InitializeRankTable>,
+        rank_width: u8,
+        total_ranked: u32,
     ) -> Result<()> {
-        msg!("Starting NFT index extraction from program logs");
+        if (rank_width != 2 && rank_width != 4) || (rank_width == 2 && total_ranked > u16::MAX as u32) {
+            msg!("Rank width must be 2 or 4 bytes and hold {} ranks", total_ranked);
+            return Err(error!(ErrorCode::InvalidRankWidth));
+        }
         
-        // Process fee transfer
-        msg!("Processing fee transfer of {} lamports", FEE_LAMPORTS);
-        let transfer_ix = system_instruction::transfer(
-            &ctx.accounts.minter.key(),
-            &ctx.accounts.fee_receiver.key(),
+        let rank_table = &mut ctx.accounts.rank_table;
+        rank_table.state = ctx.accounts.state.key();
+        rank_table.bump = ctx.bumps.rank_table;
+        rank_table.rank_width = rank_width;
+        rank_table.total_ranked = total_ranked;
+        rank_table.ranks = Vec::new();
+        
+        msg!("Initialized rank table for {} items with {}-byte ranks", total_ranked, rank_width);
+        Ok(())
+    }
+
+    /// Write the ranks of a range of indices, growing the rank table as needed
+    pub fn update_rank_data(
+        ctx: Context<UpdateRankData>,
+        start_index: u64,
+        ranks: Vec<u32>,
+    ) -> Result<()> {
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
             FEE_LAMPORTS,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.fee_receiver.to_account_info(),
-                ctx.accounts.minter.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
         )?;
-        msg!("Fee transfer successful");
         
-        // The pattern we're looking for in the program logs appears as: "8 886 888 1111"
-        // where 886 is the NFT index we need
+        let rank_table = &mut ctx.accounts.rank_table;
+        let end_index = start_index + ranks.len() as u64;
+        if !rank_table.holds_indices(end_index) {
+            msg!("Rank table holds {} indices", rank_table.total_ranked);
+            return Err(error!(ErrorCode::IndexOutOfBounds));
+        }
         
-        // For security, we also need to verify that the instruction we're analyzing
-        // is actually from the Menagerie program
+        // Extend the table if needed; new entries start out unranked
+        if end_index as usize > rank_table.index_count() {
+            let size = end_index as usize * rank_table.rank_width as usize;
+            rank_table.ranks.resize(size, 0);
+        }
         
-        // Get the menagerie transaction instruction
-        let ix_sysvar = &ctx.accounts.instructions_sysvar;
-        let current_ix = load_instruction_at_checked(0, ix_sysvar)?;
+        for (i, rank) in ranks.iter().enumerate() {
+            if !rank_table.is_valid_rank(*rank) {
+                msg!("Rank {} at index {} is outside 1..={}", rank, start_index as usize + i, rank_table.total_ranked);
+                return Err(error!(ErrorCode::InvalidRank));
+            }
+            rank_table.set_rank(start_index as usize + i, *rank);
+        }
         
-        // Check if current instruction is from our program
-        if current_ix.program_id != crate::id() {
-            msg!("Current instruction is not from our program");
-            return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+        msg!("Updated ranks {} to {}", start_index, end_index);
+        Ok(())
+    }
+
+    /// Read-only query for the rank of an index (no fee, suitable for simulation)
+    pub fn get_rank(ctx: Context<QueryRank>, index: u64) -> Result<u32> {
+        let rank = rank_at(&ctx.accounts.rank_table, index)?;
+        msg!("NFT at index {} has rank {} of {}", index, rank, ctx.accounts.rank_table.total_ranked);
+        Ok(rank)
+    }
+
+    /// Validate the next Bubblegum mint by its rank ("top N" or a percentile) instead of its score
+    pub fn validate_rank(
+        ctx: Context<ValidateRank>,
+        requirement: RankRequirement,
+        num_minted: u64,
+        dry_run: bool,
+    ) -> Result<RankResult> {
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
         }
         
-        // Look for the Menagerie MintCore instruction (previous instruction)
-        let menagerie_ix_pos = get_instruction_relative(-1, ix_sysvar)?;
-        let menagerie_ix = menagerie_ix_pos;
+        let state = &ctx.accounts.state;
+        let rank_table = &ctx.accounts.rank_table;
+        let asset_id = get_asset_id(&state.merkle_tree, num_minted);
+        msg!("Asset ID at nonce {}: {}", num_minted, asset_id);
         
+        let mut index = None;
+        let ranked = asset_mint_index(state, &asset_id).and_then(|mint_index| {
+            index = Some(mint_index);
+            rank_at(rank_table, mint_index)
+        });
         
-        // Verify it's from Menagerie program
-        if menagerie_ix.program_id != menagerie_program::id() {
-            msg!("Previous instruction is not from Menagerie program");
-            return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
-        }
+        let (result, error) = match ranked {
+            Ok(rank) => (
+                RankResult {
+                    index,
+                    rank,
+                    total_ranked: rank_table.total_ranked,
+                    percentile_bps: rank_table.percentile_bps(rank),
+                    passed: rank_table.meets(rank, requirement),
+                    asset_id,
+                },
+                None,
+            ),
+            Err(err) => {
+                msg!("NFT could not be ranked: {}", err);
+                (
+                    RankResult { index, rank: 0, total_ranked: rank_table.total_ranked, percentile_bps: 0, passed: false, asset_id },
+                    Some(err),
+                )
+            }
+        };
+        msg!("Rank {} of {} ({} bps)", result.rank, result.total_ranked, result.percentile_bps);
         
-        // For this example, we're hardcoding the extraction of index 886 from the logs
-        // In a production environment, you would want to properly parse the logs
-        // but that's not directly accessible in an on-chain program
+        emit!(RankValidated {
+            state: state.key(),
+            asset_id,
+            index: result.index,
+            rank: result.rank,
+            total_ranked: result.total_ranked,
+            requirement,
+            passed: result.passed,
+            dry_run,
+            minter: ctx.accounts.minter.key(),
+            error_code: error.as_ref().map(program_error_code),
+        });
         
-        // For this demonstration, we'll use index 886 (the one from our example transaction)
-        let nft_index = 886;
-        msg!("Using NFT index from logs: {}", nft_index);
+        if dry_run {
+            return Ok(result);
+        }
+        if let Some(err) = error {
+            return Err(err);
+        }
+        if !result.passed {
+            msg!("Rank {} does not meet the requirement", result.rank);
+            return Err(error!(ErrorCode::RankNotMet));
+        }
         
-        // Validate the NFT rarity
-        validate_nft_rarity(&ctx.accounts.state, nft_index, min_rarity_percentage)?;
+        let minter_stats = find_minter_stats(ctx.remaining_accounts, &state.key(), &ctx.accounts.minter.key());
+        let score = index.and_then(|i| rarity_at(state, i).ok()).unwrap_or(0);
+        update_minter_stats(minter_stats, |stats| {
+            record_validation(stats, true, state.score_percent(score), FEE_LAMPORTS)
+        })?;
+        Ok(result)
+    }
+
+    /// Create the ring buffer that keeps the latest `capacity` recorded mints of a rarity state
+    pub fn initialize_mint_history(ctx: Context<InitializeMintHistory>, capacity: u32) -> Result<()> {
+        if capacity == 0 || capacity > MAX_MINT_HISTORY_CAPACITY {
+            msg!("Mint history capacity must be 1 to {}", MAX_MINT_HISTORY_CAPACITY);
+            return Err(error!(ErrorCode::InvalidMintHistoryCapacity));
+        }
         
-        // Build and log the expected URI
-        let ipfs_hash = "QmeFBDa3FJQDCmUSCBmWQS3sH89GUvm8KhgCFuNTmV129H";
-        let uri = format!("https://gateway.pinit.io/ipfs/{}/{}.json", ipfs_hash, nft_index);
-        msg!("Expected URI: {}", uri);
+        let mint_history = &mut ctx.accounts.mint_history;
+        mint_history.state = ctx.accounts.state.key();
+        mint_history.bump = ctx.bumps.mint_history;
+        mint_history.capacity = capacity;
+        mint_history.head = 0;
+        mint_history.records = Vec::new();
         
-        msg!("Validation successful for NFT index: {}", nft_index);
+        msg!("Initialized mint history keeping the latest {} mints", capacity);
         Ok(())
     }
-}
 
-// Helper function to predict asset ID (similar to Bubblegum's get_asset_id)
-fn get_asset_id(tree_id: &Pubkey, nonce: u64) -> Pubkey {
-    Pubkey::find_program_address(
-        &[
-            ASSET_PREFIX,
-            tree_id.as_ref(),
-            &nonce.to_le_bytes(),
-        ],
-        &bubblegum_program::id()
-    ).0
-}
+    /// Create the signer's statistics account for a rarity state, so validations can update it
+    pub fn initialize_minter_stats(ctx: Context<InitializeMinterStats>) -> Result<()> {
+        let minter_stats = &mut ctx.accounts.minter_stats;
+        minter_stats.state = ctx.accounts.state.key();
+        minter_stats.minter = ctx.accounts.minter.key();
+        minter_stats.bump = ctx.bumps.minter_stats;
+        
+        msg!("Initialized minter stats for {}", minter_stats.minter);
+        Ok(())
+    }
 
-// Helper to extract mint instruction data from transaction
-fn get_mint_instruction_from_tx(
-    ix_sysvar: &AccountInfo,
-    position: usize,
-) -> Result<Option<Instruction>> {
-    // Load instruction at the given position
-    if let Ok(ix) = load_instruction_at_checked(position, ix_sysvar) {
-        // Check if this is a mint instruction from Menagerie
-        if ix.program_id == menagerie_program::id() {
-            // Check if first 8 bytes match the mint discriminator
-            if ix.data.len() >= 8 && ix.data[0..8] == menagerie_program::MINT_DISCRIMINATOR {
-                return Ok(Some(ix));
+    /// Record a Bubblegum or MPL Core mint made earlier in the same transaction by the minter.
+    /// Pass the created Core asset for Core mints (a CreateV1/CreateV2 into the state's
+    /// core_collection), or the tree config for Bubblegum mints into the state's tree.
+    /// The record is also stored in the asset's ["mint-record", state, asset_id] PDA, which the
+    /// instruction creates, so the same asset can never be recorded twice.
+    pub fn record_mint(ctx: Context<RecordMint>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let ix_sysvar = &ctx.accounts.instructions_sysvar;
+        let minter = ctx.accounts.minter.key();
+        let (asset_id, index) = match (&ctx.accounts.asset_account, &ctx.accounts.tree_config) {
+            (Some(asset_account), _) => {
+                // The asset must be a Core asset created by an earlier MPL Core instruction in this transaction
+                if *asset_account.owner != mpl_core_program::id() {
+                    msg!("Asset {} is not an MPL Core asset", asset_account.key());
+                    return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+                }
+                let create_ix = find_core_create(ix_sysvar, &asset_account.key())?;
+                
+                let base_asset = BaseAssetV1::deserialize(&mut &asset_account.try_borrow_data()?[..])?;
+                msg!("Name: {}", base_asset.name);
+                msg!("URI: {}", base_asset.uri);
+                
+                // Only record assets of the collection this state tracks
+                if state.core_collection == Pubkey::default() ||
+                   base_asset.update_authority != UpdateAuthority::Collection(state.core_collection) {
+                    msg!("Asset {} is not part of collection {}", asset_account.key(), state.core_collection);
+                    return Err(error!(ErrorCode::CollectionMismatch));
+                }
+                
+                // The minter must have paid for or received the asset
+                let payer = create_ix.accounts.get(mpl_core_program::PAYER_ACCOUNT_INDEX).map(|meta| meta.pubkey);
+                if base_asset.owner != minter && payer != Some(minter) {
+                    msg!("Minter {} did not pay for or receive asset {}", minter, asset_account.key());
+                    return Err(error!(ErrorCode::MinterMismatch));
+                }
+                
+                (asset_account.key(), extract_index(state, &base_asset.name, &base_asset.uri)?)
+            }
+            (None, Some(tree_config)) => {
+                let (mint_ix, metadata) = find_bubblegum_mint(ix_sysvar, &ctx.accounts.merkle_tree.key())?;
+                msg!("Name: {}", metadata.name);
+                msg!("URI: {}", metadata.uri);
+                
+                // The minter must have paid for or received the leaf
+                let account_at = |position: usize| mint_ix.accounts.get(position).map(|meta| meta.pubkey);
+                if account_at(bubblegum_program::LEAF_OWNER_ACCOUNT_INDEX) != Some(minter) &&
+                   account_at(bubblegum_program::PAYER_ACCOUNT_INDEX) != Some(minter) {
+                    msg!("Minter {} did not pay for or receive the Bubblegum mint", minter);
+                    return Err(error!(ErrorCode::MinterMismatch));
+                }
+                
+                // mint_ix is the latest mint into the tree before us, so it took the nonce before the current count
+                let tree_config = TreeConfig::try_deserialize(&mut &tree_config.try_borrow_data()?[..])?;
+                if tree_config.num_minted == 0 {
+                    return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+                }
+                let asset_id = get_asset_id(&state.merkle_tree, tree_config.num_minted - 1);
+                (asset_id, extract_index(state, &metadata.name, &metadata.uri)?)
+            }
+            (None, None) => {
+                msg!("Either the asset account or the tree config must be provided");
+                return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
             }
+        };
+        
+        let mint_history = &mut ctx.accounts.mint_history;
+        
+        // Verify the per-asset record PDA, which only exists once the asset is recorded
+        let mint_record = &ctx.accounts.mint_record;
+        let (address, mint_record_bump) = Pubkey::find_program_address(
+            &[b"mint-record", state.key().as_ref(), asset_id.as_ref()],
+            &crate::id(),
+        );
+        if mint_record.key() != address {
+            msg!("Mint record account must be {}", address);
+            return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+        }
+        if *mint_record.owner == crate::id() {
+            msg!("Mint of {} is already recorded", asset_id);
+            return Err(error!(ErrorCode::MintAlreadyRecorded));
         }
         
-        // Check if this is a mint instruction from Bubblegum
-        if ix.program_id == bubblegum_program::id() {
-            // Check if first 8 bytes match either of the mint discriminators
-            if ix.data.len() >= 8 && 
-               (ix.data[0..8] == bubblegum_program::MINT_V1_DISCRIMINATOR ||
-                ix.data[0..8] == bubblegum_program::MINT_TO_COLLECTION_V1_DISCRIMINATOR) {
-                return Ok(Some(ix));
+        let rarity_score = rarity_at(state, index).ok().map(|score| state.score_percent(score));
+        let timestamp = Clock::get()?.unix_timestamp;
+        
+        let state = &mut ctx.accounts.state;
+        
+        // Track the index difference to the previously recorded mint
+        if let Some(previous) = mint_history.iter().rev().find_map(|r| r.mint_index) {
+            let difference = index_difference(previous, index, state.map_len() as u64);
+            msg!("Index difference to previous mint: {}", difference);
+            record_difference(&mut state.mint_patterns, difference);
+        }
+        
+        state.total_mints += 1;
+        let mint_count = state.total_mints;
+        
+        // Count the mint for the minter and the state's leaderboard
+        let minter_stats = &mut ctx.accounts.minter_stats;
+        if minter_stats.minter == Pubkey::default() {
+            minter_stats.state = state.key();
+            minter_stats.minter = minter;
+            minter_stats.bump = ctx.bumps.minter_stats;
+        }
+        minter_stats.mints += 1;
+        update_leaderboard(&mut state.top_minters, minter, minter_stats.mints);
+        let record = MintRecord {
+            mint_index: Some(index),
+            asset_id,
+            mint_count,
+            rarity_score,
+            minter,
+            timestamp,
+        };
+        
+        let state_key = state.key();
+        create_program_account(
+            &ctx.accounts.minter,
+            mint_record,
+            &ctx.accounts.system_program,
+            MINT_RECORD_ACCOUNT_SIZE,
+            &[b"mint-record", state_key.as_ref(), asset_id.as_ref(), &[mint_record_bump]],
+        )?;
+        
+        let record_account = MintRecordAccount {
+            state: state_key,
+            bump: mint_record_bump,
+            record: record.clone(),
+        };
+        record_account.try_serialize(&mut &mut mint_record.try_borrow_mut_data()?[..])?;
+        msg!("Stored mint record at {}", mint_record.key());
+        mint_history.push(record);
+        msg!("Recorded mint {} of {} at index {} with rarity score {:?}", state.total_mints, asset_id, index, rarity_score);
+        
+        emit!(MintRecorded {
+            state: state.key(),
+            asset_id,
+            index: Some(index),
+            score: rarity_score,
+            minter,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    /// Get statistics about mint patterns and rarity score distribution, returned as
+    /// `MintStatistics` return data and event; `log` also writes them to the program logs
+    pub fn get_mint_statistics(ctx: Context<GetMintStatistics>, log: bool) -> Result<MintStatistics> {
+        let state = &ctx.accounts.state;
+        
+        // Recorded mints from oldest to newest, falling back to records stored on older states
+        let records: Vec<&MintRecord> = match &ctx.accounts.mint_history {
+            Some(mint_history) => mint_history.iter().collect(),
+            None => state.mint_records.iter().collect(),
+        };
+        
+        // Count NFTs in each rarity tier. Records only keep a whole percentage, so the score is
+        // read back from the rarity map in the state's unit where the index still has one.
+        let thresholds = state.thresholds();
+        let mut tier_counts = vec![0u32; thresholds.len() + 1];
+        let mut records_with_rarity = 0;
+        for record in records.iter() {
+            let percent = match record.rarity_score {
+                Some(percent) => percent,
+                None => continue,
+            };
+            let score = record
+                .mint_index
+                .and_then(|index| state.score(index))
+                .filter(|score| *score != state.unknown_score())
+                .unwrap_or_else(|| state.score_from_percent(percent));
+            tier_counts[rarity_tier(&thresholds, score) as usize] += 1;
+            records_with_rarity += 1;
+        }
+        
+        // Sort patterns by occurrences (descending)
+        let mut top_patterns = state.mint_patterns.clone();
+        top_patterns.sort_by(|a, b| b.occurrences.cmp(&a.occurrences));
+        top_patterns.truncate(MAX_REPORTED_PATTERNS);
+        
+        let statistics = MintStatistics {
+            total_mints: state.total_mints,
+            records_in_history: records.len() as u32,
+            records_with_rarity,
+            tier_counts,
+            top_patterns,
+            top_minters: state.top_minters.clone(),
+        };
+        
+        if log {
+            log_mint_statistics(&thresholds, &statistics);
+        }
+        
+        emit!(MintStatisticsReported {
+            state: state.key(),
+            statistics: statistics.clone(),
+        });
+        Ok(statistics)
+    }
+
+    /// Validate a MintCore instruction by extracting URI data and checking the rarity
+    pub fn validate_mint_core(
+        ctx: Context<ValidateMintCore>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_mint_core with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let state = &ctx.accounts.state;
+        let scored = score_core_asset(state, &ctx.accounts.asset_account);
+        
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            ctx.accounts.asset_account.key(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Validate a Bubblegum mint in the same transaction by extracting the index from its metadata
+    pub fn validate_bubblegum_mint(
+        ctx: Context<ValidateBubblegumMint>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_bubblegum_mint with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let state = &ctx.accounts.state;
+        let scored = score_bubblegum_mint(state, &ctx.accounts.instructions_sysvar, &ctx.accounts.merkle_tree.key());
+        
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            Pubkey::default(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Validate a legacy Token Metadata NFT by deserializing its Metadata account
+    pub fn validate_token_metadata(
+        ctx: Context<ValidateTokenMetadata>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_token_metadata with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let state = &ctx.accounts.state;
+        let scored = score_token_metadata(state, &ctx.accounts.metadata_account, &ctx.accounts.mint_account.key());
+        
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            ctx.accounts.mint_account.key(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Predict the config line the next Candy Machine v3 mint receives and validate its rarity.
+    /// Must run in the same transaction as the mint, since the selection depends on the slot.
+    pub fn validate_candy_machine_mint(
+        ctx: Context<ValidateCandyMachineMint>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_candy_machine_mint with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let state = &ctx.accounts.state;
+        let scored = score_candy_machine_mint(state, &ctx.accounts.candy_machine, &ctx.accounts.recent_slothashes);
+        
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            Pubkey::default(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Create the trait frequency table used to score MPL Core assets by their attributes
+    pub fn initialize_trait_table(
+        ctx: Context<InitializeTraitTable>,
+        update_authority: Pubkey,
+        total_supply: u32,
+    ) -> Result<()> {
+        let trait_table = &mut ctx.accounts.trait_table;
+        trait_table.state = ctx.accounts.state.key();
+        trait_table.update_authority = update_authority;
+        trait_table.bump = ctx.bumps.trait_table;
+        trait_table.total_supply = total_supply;
+        trait_table.traits = Vec::new();
+        
+        msg!("Initialized trait table for {} items of {}", total_supply, update_authority);
+        Ok(())
+    }
+
+    /// Append trait frequencies to the trait table, optionally clearing existing entries first
+    pub fn upload_trait_frequencies(
+        ctx: Context<UploadTraitFrequencies>,
+        clear_existing: bool,
+        traits: Vec<TraitFrequency>,
+    ) -> Result<()> {
+        charge_fee(
+            &ctx.accounts.authority,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
+            FEE_LAMPORTS,
+        )?;
+        
+        let trait_table = &mut ctx.accounts.trait_table;
+        if clear_existing {
+            trait_table.traits.clear();
+        }
+        
+        for frequency in traits.into_iter() {
+            if frequency.count > trait_table.total_supply {
+                msg!("Trait {}: {} count {} exceeds total supply", frequency.trait_type, frequency.value, frequency.count);
+                return Err(error!(ErrorCode::InvalidTraitFrequency));
+            }
+            
+            // Overwrite the count of traits that were already uploaded
+            match trait_table.traits.iter_mut().find(|t| t.same_trait(&frequency)) {
+                Some(existing) => existing.count = frequency.count,
+                None => trait_table.traits.push(frequency),
             }
         }
+        
+        msg!("Trait table now holds {} trait values", trait_table.traits.len());
+        emit!(TraitTableUpdated {
+            trait_table: trait_table.key(),
+            trait_count: trait_table.traits.len() as u32,
+        });
+        Ok(())
+    }
+
+    /// Validate an MPL Core asset by scoring the traits in its Attributes plugin
+    pub fn validate_core_attributes(
+        ctx: Context<ValidateCoreAttributes>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_core_attributes with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let asset_info = ctx.accounts.asset_account.to_account_info();
+        let scored = score_core_attributes(&ctx.accounts.state, &ctx.accounts.trait_table, &asset_info);
+        
+        finish_validation(
+            &ctx.accounts.state,
+            scored,
+            min_score,
+            asset_info.key(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Validate a Token-2022 NFT by extracting the index from its token-metadata extension
+    pub fn validate_token_2022(
+        ctx: Context<ValidateToken2022>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_token_2022 with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let state = &ctx.accounts.state;
+        let scored = score_token_2022(state, &ctx.accounts.mint_account);
+        
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            ctx.accounts.mint_account.key(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Validate a Token-2022 NFT by scoring its additional metadata key/value pairs as traits
+    pub fn validate_token_2022_traits(
+        ctx: Context<ValidateToken2022Traits>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_token_2022_traits with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let scored = score_token_2022_traits(&ctx.accounts.state, &ctx.accounts.trait_table, &ctx.accounts.mint_account);
+        
+        finish_validation(
+            &ctx.accounts.state,
+            scored,
+            min_score,
+            ctx.accounts.mint_account.key(),
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Register a launchpad program and the mint instructions its NFT index can be read from
+    pub fn register_launchpad_adapter(
+        ctx: Context<RegisterLaunchpadAdapter>,
+        program_id: Pubkey,
+        instructions: Vec<AdapterInstruction>,
+    ) -> Result<()> {
+        validate_adapter_instructions(&instructions)?;
+        
+        let launchpad_adapter = &mut ctx.accounts.launchpad_adapter;
+        launchpad_adapter.program_id = program_id;
+        launchpad_adapter.bump = ctx.bumps.launchpad_adapter;
+        launchpad_adapter.instructions = instructions;
+        
+        msg!(
+            "Registered launchpad adapter for {} with {} instructions",
+            program_id,
+            launchpad_adapter.instructions.len()
+        );
+        emit!(LaunchpadAdapterUpdated {
+            program_id: launchpad_adapter.program_id,
+            instruction_count: launchpad_adapter.instructions.len() as u32,
+        });
+        Ok(())
+    }
+
+    /// Replace the recognized mint instructions of a registered launchpad
+    pub fn update_launchpad_adapter(
+        ctx: Context<UpdateLaunchpadAdapter>,
+        instructions: Vec<AdapterInstruction>,
+    ) -> Result<()> {
+        validate_adapter_instructions(&instructions)?;
+        
+        let launchpad_adapter = &mut ctx.accounts.launchpad_adapter;
+        launchpad_adapter.instructions = instructions;
+        
+        msg!(
+            "Updated launchpad adapter for {} with {} instructions",
+            launchpad_adapter.program_id,
+            launchpad_adapter.instructions.len()
+        );
+        emit!(LaunchpadAdapterUpdated {
+            program_id: launchpad_adapter.program_id,
+            instruction_count: launchpad_adapter.instructions.len() as u32,
+        });
+        Ok(())
+    }
+
+    /// Validate a mint from any registered launchpad in the same transaction,
+    /// using the adapter's rule to locate the NFT index
+    pub fn validate_launchpad_mint(
+        ctx: Context<ValidateLaunchpadMint>,
+        min_score: u16,
+        dry_run: bool,
+    ) -> Result<RarityResult> {
+        msg!("Starting validate_launchpad_mint with min_score: {}", min_score);
+        
+        if !dry_run {
+            charge_fee(
+                &ctx.accounts.minter,
+                &ctx.accounts.fee_receiver,
+                &ctx.accounts.system_program,
+                FEE_LAMPORTS,
+            )?;
+        }
+        
+        let state = &ctx.accounts.state;
+        let mut asset_id = Pubkey::default();
+        let asset_account = ctx.accounts.asset_account.as_ref().map(|a| a.to_account_info());
+        let scored = score_launchpad_mint(
+            state,
+            &ctx.accounts.launchpad_adapter,
+            &ctx.accounts.instructions_sysvar,
+            asset_account.as_ref(),
+            &mut asset_id,
+        );
+        
+        finish_validation(
+            state,
+            scored,
+            min_score,
+            asset_id,
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            dry_run,
+        )
+    }
+
+    /// Debug instruction for analyzing Menagerie MintCore instructions
+    pub fn debug_mint_core_instruction(
+        ctx: Context<DebugMintInstruction>,
+        target_slot: Option<u64>,
+    ) -> Result<()> {
+        msg!("Starting debug for MintCore instruction");
+        
+        // Get instructions sysvar
+        let ix_sysvar = &ctx.accounts.instructions_sysvar;
+        
+        // Get current instruction
+        let current_ix = load_instruction_at_checked(0, ix_sysvar)?;
+        if current_ix.program_id != crate::id() {
+            msg!("Current instruction is not from our program");
+            return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+        }
+        
+        // Try to find the Menagerie instruction
+        // First check the previous instruction
+        let prev_ix = load_instruction_at_checked(0, ix_sysvar)?;
+        
+        msg!("Previous instruction program: {}", prev_ix.program_id);
+        
+        // If it's from Menagerie, analyze it
+        if prev_ix.program_id == menagerie_program::id() {
+            analyze_menagerie_instruction(&prev_ix)?;
+        } else {
+            // Try to find Menagerie instruction in transaction
+            let mut found = false;
+            for i in 0..5 {  // Try first 5 instructions
+                let relative_index = -(i as i64 + 1);
+                let ix_pos = get_instruction_relative(relative_index, ix_sysvar);
+                if ix_pos.is_err() {
+                    continue;
+                }
+                
+                let ix_result = ix_pos;
+                if let Ok(ix) = ix_result {
+                    if ix.program_id == menagerie_program::id() {
+                        analyze_menagerie_instruction(&ix)?;
+                        found = true;
+                        break;
+                    }
+                }
+            }
+            
+            if !found {
+                msg!("No Menagerie instruction found in recent transaction history");
+            }
+        }
+        
+        // Print out the URI template we expect to match
+        let uri_template = &ctx.accounts.state.uri_template;
+        if uri_template.base_uri.is_empty() {
+            msg!("No URI template configured");
+        } else {
+            msg!("Expected base URI: {}", uri_template.base_uri);
+            msg!("Expected URI suffix: {}", uri_template.suffix);
+        }
+        
+        Ok(())
+    }
+
+    /// Validate the NFT minted by an earlier launchpad instruction in this transaction.
+    /// Kept for existing Menagerie integrations with its original arguments (a whole-percent
+    /// threshold) and accounts. Passing a registered launchpad adapter after them resolves the
+    /// mint as in validate_launchpad_mint; otherwise the previous instruction must be a Menagerie
+    /// mint, whose index is taken from the created asset, its instruction data or its return data.
+    pub fn extract_nft_index_from_logs(
+        ctx: Context<ValidateMintFromLogs>,
+        min_rarity_percentage: u8,
+    ) -> Result<RarityResult> {
+        msg!("Starting NFT index extraction from program logs");
+        
+        charge_fee(
+            &ctx.accounts.minter,
+            &ctx.accounts.fee_receiver,
+            &ctx.accounts.system_program,
+            FEE_LAMPORTS,
+        )?;
+        
+        let state = &ctx.accounts.state;
+        let mut asset_id = Pubkey::default();
+        let launchpad_adapter = ctx.accounts.launchpad_adapter.as_deref();
+        let asset_account = ctx.accounts.asset_account.as_ref().map(|a| a.to_account_info());
+        let scored = score_mint_from_logs(
+            state,
+            launchpad_adapter,
+            &ctx.accounts.instructions_sysvar,
+            asset_account.as_ref(),
+            &mut asset_id,
+        );
+        
+        finish_validation(
+            state,
+            scored,
+            state.score_from_percent(min_rarity_percentage),
+            asset_id,
+            ctx.accounts.minter.key(),
+            ctx.remaining_accounts,
+            false,
+        )
     }
-    
-    Ok(None)
 }
 
-// Bubblegum Tree Config structure (simplified for our needs)
-#[account]
-pub struct TreeConfig {
-    pub tree_creator: Pubkey,
-    pub tree_delegate: Pubkey,
-    pub total_mint_capacity: u64,
-    pub num_minted: u64,
-    pub is_public: bool,
+// Helper to write mint statistics to the program logs
+fn log_mint_statistics(rarity_thresholds: &[u16], statistics: &MintStatistics) {
+    msg!("=== Mint Statistics ===");
+    msg!("Total mints analyzed: {}", statistics.total_mints);
+    msg!("Mints in history: {}", statistics.records_in_history);
+    msg!("Records with rarity scores: {}", statistics.records_with_rarity);
+    
+    // Report on rarity tiers
+    if !rarity_thresholds.is_empty() {
+        msg!("Rarity tier distribution:");
+        
+        let tier_counts = &statistics.tier_counts;
+        for i in 0..tier_counts.len() {
+            let tier_name = if i == 0 {
+                "Common".to_string()
+            } else if i == tier_counts.len() - 1 {
+                "Legendary".to_string()
+            } else {
+                format!("Tier {}", i)
+            };
+            
+            let threshold = if i == 0 {
+                0
+            } else {
+                rarity_thresholds[i - 1] as u32
+            };
+            
+            msg!("{} ({}+): {} NFTs", tier_name, threshold, tier_counts[i]);
+        }
+    }
+    
+    // Report on mint patterns
+    if !statistics.top_patterns.is_empty() {
+        msg!("Mint pattern distribution:");
+        
+        for pattern in statistics.top_patterns.iter() {
+            let probability = pattern.probability * 100.0;
+            msg!(
+                "Difference of {}: {} occurrences (approx. {:.1}% probability)",
+                pattern.difference,
+                pattern.occurrences,
+                probability
+            );
+        }
+    }
+    
+    // Report on most active minters
+    if !statistics.top_minters.is_empty() {
+        msg!("Top minters:");
+        for rank in statistics.top_minters.iter() {
+            msg!("{}: {} mints", rank.minter, rank.mints);
+        }
+    }
+    
+    msg!("=== End of Statistics ===");
 }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
+// Helper to transfer a fee from the payer to the fee receiver
+fn charge_fee<'info>(
+    payer: &AccountInfo<'info>,
+    fee_receiver: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    lamports: u64,
+) -> Result<()> {
+    msg!("Processing fee transfer of {} lamports", lamports);
+    let transfer_ix = system_instruction::transfer(payer.key, fee_receiver.key, lamports);
+    anchor_lang::solana_program::program::invoke(
+        &transfer_ix,
+        &[fee_receiver.clone(), payer.clone(), system_program.clone()],
+    )?;
+    msg!("Fee transfer successful");
+    
+    emit!(FeeCharged {
+        payer: payer.key(),
+        amount: lamports,
+    });
+    Ok(())
+}
+
+// Helper function to predict asset ID (similar to Bubblegum's get_asset_id)
+fn get_asset_id(tree_id: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            ASSET_PREFIX,
+            tree_id.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        &bubblegum_program::id()
+    ).0
+}
+
+// Helper to predict the asset ID minted at a Bubblegum nonce and the rarity map index it maps to
+fn predict_mint_index(state: &RarityState, nonce: u64) -> Result<(Pubkey, u64)> {
+    let asset_id = get_asset_id(&state.merkle_tree, nonce);
+    Ok((asset_id, asset_mint_index(state, &asset_id)?))
+}
+
+// Helper to map a Bubblegum asset ID to its rarity map index
+fn asset_mint_index(state: &RarityState, asset_id: &Pubkey) -> Result<u64> {
+    // Convert the asset ID bytes to a deterministic index for our rarity map
+    let asset_id_bytes = asset_id.to_bytes();
+    let hash = keccak::hashv(&[&asset_id_bytes]);
+    let seed = u64::from_be_bytes(hash.to_bytes()[0..8].try_into().unwrap());
+    
+    // Calculate a deterministic index in our rarity map range
+    let max_items = state.map_len() as u64;
+    if max_items == 0 {
+        return Err(error!(ErrorCode::NoRarityData));
+    }
+    
+    Ok(seed % max_items)
+}
+
+// Helper to score an MPL Core asset through the state's index templates
+fn core_asset_rarity(state: &RarityState, asset_account: &AccountInfo) -> Result<u16> {
+    if *asset_account.owner != mpl_core_program::id() {
+        msg!("Asset account {} is not an MPL Core asset", asset_account.key());
+        return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+    }
+    
+    let base_asset = BaseAssetV1::deserialize(&mut &asset_account.try_borrow_data()?[..])?;
+    check_core_collection(state, &base_asset, &asset_account.key())?;
+    let index = extract_index(state, &base_asset.name, &base_asset.uri)?;
+    rarity_at(state, index)
+}
+
+// Helper to check that an MPL Core asset belongs to the state's Core collection, once one is set
+fn check_core_collection(state: &RarityState, base_asset: &BaseAssetV1, asset: &Pubkey) -> Result<()> {
+    if state.core_collection != Pubkey::default() &&
+       base_asset.update_authority != UpdateAuthority::Collection(state.core_collection) {
+        msg!("Asset {} is not part of collection {}", asset, state.core_collection);
+        return Err(error!(ErrorCode::CollectionMismatch));
+    }
+    Ok(())
+}
+
+// Helper to write the scores of an encoded payload from start_index on, returning one past
+// the last index written. The payload is decoded twice, once to validate and count it and
+// once to write it, so no decoded copy of the scores has to be held in memory. The state is
+// resized in between to fit the decoded end index (by at most 10 KB per call).
+fn write_encoded_scores(
+    accounts: &mut UpdateRarityMap,
+    start_index: u64,
+    encoding: RarityEncoding,
+    payload: &[u8],
+) -> Result<u64> {
+    let state = &accounts.state;
+    let width = state.entry_width();
+    
+    // Make sure every score is valid and we stay within bounds
+    let mut end_index = start_index;
+    decode_scores(encoding, width, payload, |score| {
+        check_score(state, score)?;
+        end_index += 1;
+        if end_index > u16::MAX as u64 {
+            return Err(error!(ErrorCode::IndexOutOfBounds));
+        }
+        Ok(())
+    })?;
+    
+    let state_info = state.to_account_info();
+    grow_program_account(
+        &state_info,
+        &accounts.authority,
+        &accounts.system_program,
+        state.space_for_map(state_info.data_len(), end_index as usize),
+    )?;
+    
+    let state = &mut accounts.state;
+    ensure_histogram(state);
+    grow_rarity_map(state, end_index as usize);
+    
+    // Update the rarity map with the decoded data
+    let mut index = start_index as usize;
+    decode_scores(encoding, width, payload, |score| {
+        write_score(state, index, score);
+        index += 1;
+        Ok(())
+    })?;
+    
+    Ok(end_index)
+}
+
+// Helper to reject scores a state cannot store
+fn check_score(state: &RarityState, score: u16) -> Result<()> {
+    if score > state.max_score() && (state.is_precise() || score >= state.unknown_score()) {
+        msg!("Score {} is out of range", score);
+        return Err(error!(ErrorCode::InvalidScore));
+    }
+    Ok(())
+}
+
+// Helper to look up the rank of an index
+fn rank_at(rank_table: &RankTable, index: u64) -> Result<u32> {
+    match rank_table.rank(index) {
+        Some(0) => {
+            msg!("Index {} has not been ranked", index);
+            Err(error!(ErrorCode::InvalidRank))
+        }
+        Some(rank) => Ok(rank),
+        None => {
+            msg!("Index {} is out of bounds for rank table of length {}", index, rank_table.index_count());
+            Err(error!(ErrorCode::IndexOutOfBounds))
+        }
+    }
+}
+
+// Helper to look up the rarity score of an index
+fn rarity_at(state: &RarityState, index: u64) -> Result<u16> {
+    match state.score(index) {
+        Some(rarity) if rarity == state.unknown_score() => {
+            msg!("Index {} has no rarity score", index);
+            Err(error!(ErrorCode::RarityUnknown))
+        }
+        Some(rarity) => Ok(rarity),
+        None => {
+            msg!("Index {} is out of bounds for rarity map of length {}", index, state.map_len());
+            Err(error!(ErrorCode::IndexOutOfBounds))
+        }
+    }
+}
+
+// Helper to extract mint instruction data from transaction
+fn get_mint_instruction_from_tx(
+    ix_sysvar: &AccountInfo,
+    position: usize,
+) -> Result<Option<Instruction>> {
+    // Load instruction at the given position
+    if let Ok(ix) = load_instruction_at_checked(position, ix_sysvar) {
+        // Check if this is a mint instruction from Menagerie
+        if ix.program_id == menagerie_program::id() {
+            // Check if first 8 bytes match the mint discriminator
+            if ix.data.len() >= 8 && ix.data[0..8] == menagerie_program::MINT_DISCRIMINATOR {
+                return Ok(Some(ix));
+            }
+        }
+        
+        // Check if this is a mint instruction from Bubblegum
+        if ix.program_id == bubblegum_program::id() {
+            // Check if first 8 bytes match either of the mint discriminators
+            if ix.data.len() >= 8 && 
+               (ix.data[0..8] == bubblegum_program::MINT_V1_DISCRIMINATOR ||
+                ix.data[0..8] == bubblegum_program::MINT_TO_COLLECTION_V1_DISCRIMINATOR) {
+                return Ok(Some(ix));
+            }
+        }
+    }
+    
+    Ok(None)
+}
+
+// Helper to find the closest Bubblegum mint into the given tree that ran before us, with its metadata
+fn find_bubblegum_mint(
+    ix_sysvar: &AccountInfo,
+    merkle_tree: &Pubkey,
+) -> Result<(Instruction, MetadataArgs)> {
+    for ix in preceding_instructions(ix_sysvar)? {
+        if ix.program_id != bubblegum_program::id() || ix.data.len() < 8 {
+            continue;
+        }
+        
+        // Both mint_v1 and mint_to_collection_v1 start their args with MetadataArgs
+        if ix.data[0..8] != bubblegum_program::MINT_V1_DISCRIMINATOR &&
+           ix.data[0..8] != bubblegum_program::MINT_TO_COLLECTION_V1_DISCRIMINATOR {
+            continue;
+        }
+        
+        // Only accept mints into the tree this state is associated with
+        match ix.accounts.get(bubblegum_program::MERKLE_TREE_ACCOUNT_INDEX) {
+            Some(meta) if meta.pubkey == *merkle_tree => {}
+            _ => continue,
+        }
+        
+        let metadata = MetadataArgs::deserialize(&mut &ix.data[8..]).map_err(|_| {
+            msg!("Failed to deserialize Bubblegum metadata");
+            error!(ErrorCode::MintTransactionAnalysisFailed)
+        })?;
+        return Ok((ix, metadata));
+    }
+    
+    msg!("No Bubblegum mint into tree {} found before this instruction", merkle_tree);
+    Err(error!(ErrorCode::MintTransactionAnalysisFailed))
+}
+
+// Helper to find the MPL Core CreateV1/CreateV2 instruction of an asset that ran before us
+fn find_core_create(ix_sysvar: &AccountInfo, asset: &Pubkey) -> Result<Instruction> {
+    for ix in preceding_instructions(ix_sysvar)? {
+        if ix.program_id != mpl_core_program::id() || ix.data.is_empty() {
+            continue;
+        }
+        
+        if ix.data[0] != mpl_core_program::CREATE_V1_DISCRIMINATOR &&
+           ix.data[0] != mpl_core_program::CREATE_V2_DISCRIMINATOR {
+            continue;
+        }
+        
+        match ix.accounts.get(mpl_core_program::ASSET_ACCOUNT_INDEX) {
+            Some(meta) if meta.pubkey == *asset => return Ok(ix),
+            _ => continue,
+        }
+    }
+    
+    msg!("No MPL Core create of {} found before this instruction", asset);
+    Err(error!(ErrorCode::MintTransactionAnalysisFailed))
+}
+
+// Helper to create a PDA owned by this program, also when someone has already sent lamports to the address
+fn create_program_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer_seeds = &[seeds];
+    
+    if account.lamports() == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::id(),
+        );
+    }
+    
+    let shortfall = rent.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate { account_to_allocate: account.clone() },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Assign { account_to_assign: account.clone() },
+            signer_seeds,
+        ),
+        &crate::id(),
+    )
+}
+
+// Helper to grow a program-owned account to `space` bytes, topping up its rent from the payer.
+// Accounts can grow by at most MAX_PERMITTED_DATA_INCREASE bytes per instruction.
+fn grow_program_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    let current = account.data_len();
+    if space <= current {
+        return Ok(());
+    }
+    if space - current > MAX_PERMITTED_DATA_INCREASE {
+        msg!("Account {} can grow by at most {} bytes per call", account.key(), MAX_PERMITTED_DATA_INCREASE);
+        return Err(error!(ErrorCode::AccountGrowthTooLarge));
+    }
+    
+    let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    account.realloc(space, false)?;
+    Ok(())
+}
+
+// Helper to compute the account size of serialized data plus the 8-byte discriminator,
+// counting the bytes instead of buffering a copy of them
+fn serialized_space<T: AnchorSerialize>(value: &T) -> Result<usize> {
+    struct ByteCounter(usize);
+    
+    impl std::io::Write for ByteCounter {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0 += bytes.len();
+            Ok(bytes.len())
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    let mut counter = ByteCounter(0);
+    value.serialize(&mut counter).map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+    Ok(8 + counter.0)
+}
+
+// Helper to finish an UpdateConfig instruction: record the tree on states initialized before
+// initialize stored it (their merkle_tree is still the default key), then grow the state so
+// the updated config fits
+fn finish_config_update(accounts: &mut UpdateConfig) -> Result<()> {
+    if accounts.state.merkle_tree == Pubkey::default() {
+        accounts.state.merkle_tree = accounts.merkle_tree.key();
+    }
+    
+    let state_info = accounts.state.to_account_info();
+    grow_program_account(
+        &state_info,
+        &accounts.authority,
+        &accounts.system_program,
+        serialized_space(&*accounts.state)?,
+    )
+}
+
+// Bubblegum Tree Config structure (simplified for our needs)
+#[account]
+pub struct TreeConfig {
+    pub tree_creator: Pubkey,
+    pub tree_delegate: Pubkey,
+    pub total_mint_capacity: u64,
+    pub num_minted: u64,
+    pub is_public: bool,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<RarityState>()*10,
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// CHECK: This is the merkle tree we're associating with our rarity state
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: We need this to read the max depth and buffer size
+    pub merkle_tree_account: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRarityMap<'info> {
+    // Resized by the instruction to fit the rarity map it writes
+    #[account(
+        mut,
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    // Resized by the instruction to fit the updated config
+    #[account(
+        mut,
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateMint<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.to_account_info().key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// The merkle tree account
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    #[account(mut, address=fee_receiver::id())] 
+    pub fee_receiver: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AnalyzeMintTransaction<'info> {
+    #[account(
+        mut,
+        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// The merkle tree account
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// The tree config account to get the current mint count
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum_program::id(),
+    )]
+    pub tree_config: Account<'info, TreeConfig>,
+    
+    /// CHECK: This account is the mint account from the transaction we're analyzing
+    pub mint_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This account is the metadata account for the mint
+    #[account(
+        seeds = [METADATA_PREFIX, metadata_program::id().as_ref(), mint_account.key().as_ref()],
+        bump,
+        seeds::program = metadata_program::id(),
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender
+    pub transaction_sender: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the sysvar instructions account
+    pub instructions_sysvar: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMintHistory<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = MINT_HISTORY_SIZE,
+        seeds = [b"mint-history", state.key().as_ref()],
+        bump
+    )]
+    pub mint_history: Account<'info, MintHistory>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMinterStats<'info> {
+    #[account(
+        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        init,
+        payer = minter,
+        space = MINTER_STATS_SIZE,
+        seeds = [b"minter-stats", state.key().as_ref(), minter.key().as_ref()],
+        bump
+    )]
+    pub minter_stats: Account<'info, MinterStats>,
+    
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordMint<'info> {
+    #[account(
+        mut,
+        realloc = state.to_account_info().data_len()
+            + if state.mint_patterns.len() < MAX_MINT_PATTERNS { MINT_PATTERN_SIZE } else { 0 }
+            + if state.top_minters.len() < MAX_TOP_MINTERS { MINTER_RANK_SIZE } else { 0 },
+        realloc::zero = false,
+        realloc::payer = minter,
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        mut,
+        realloc = mint_history.space_for_next().max(mint_history.to_account_info().data_len()),
+        realloc::zero = false,
+        realloc::payer = minter,
+        seeds = [b"mint-history", state.key().as_ref()],
+        bump = mint_history.bump,
+    )]
+    pub mint_history: Account<'info, MintHistory>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = minter,
+        space = MINTER_STATS_SIZE,
+        seeds = [b"minter-stats", state.key().as_ref(), minter.key().as_ref()],
+        bump
+    )]
+    pub minter_stats: Account<'info, MinterStats>,
+    
+    /// CHECK: Core asset created by the mint; omit for Bubblegum mints
+    pub asset_account: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Bubblegum tree config of the merkle tree; required for Bubblegum mints
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum_program::id(),
+    )]
+    pub tree_config: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: The asset's ["mint-record", state, asset_id] PDA, verified and created by the instruction
+    #[account(mut)]
+    pub mint_record: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the sysvar instructions account
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetMintStatistics<'info> {
+    #[account(
+        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        seeds = [b"mint-history", state.key().as_ref()],
+        bump = mint_history.bump,
+    )]
+    pub mint_history: Option<Account<'info, MintHistory>>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateBatch<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// CHECK: This is the key the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QueryRarity<'info> {
+    #[account(
+        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+}
+
+#[derive(Accounts)]
+pub struct QueryRank<'info> {
+    #[account(
+        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        seeds = [b"rank-table", state.key().as_ref()],
+        bump = rank_table.bump,
+    )]
+    pub rank_table: Account<'info, RankTable>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRankTable<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = RANK_TABLE_SIZE,
+        seeds = [b"rank-table", state.key().as_ref()],
+        bump
+    )]
+    pub rank_table: Account<'info, RankTable>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_index: u64, ranks: Vec<u32>)]
+pub struct UpdateRankData<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        mut,
+        realloc = RANK_TABLE_SIZE + rank_table.ranks.len().max(
+            (start_index as usize).saturating_add(ranks.len()).saturating_mul(rank_table.rank_width as usize)
+        ),
+        realloc::zero = false,
+        realloc::payer = authority,
+        seeds = [b"rank-table", state.key().as_ref()],
+        bump = rank_table.bump,
+    )]
+    pub rank_table: Account<'info, RankTable>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateRank<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    #[account(
+        seeds = [b"rank-table", state.key().as_ref()],
+        bump = rank_table.bump,
+    )]
+    pub rank_table: Account<'info, RankTable>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateMintCore<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.to_account_info().key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// The merkle tree account
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: The MPL Core asset to validate, deserialized by the instruction
+    #[account(owner = mpl_core_program::id())]
+    pub asset_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    /// CHECK: This is the sysvar instructions account
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateBubblegumMint<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// CHECK: This is the merkle tree the Bubblegum mint must target
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    /// CHECK: This is the sysvar instructions account
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateTokenMetadata<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// CHECK: This is the key the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: This is the mint of the NFT being validated
+    pub mint_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the Metadata PDA of the mint, deserialized in the instruction
+    #[account(
+        seeds = [METADATA_PREFIX, metadata_program::id().as_ref(), mint_account.key().as_ref()],
+        bump,
+        seeds::program = metadata_program::id(),
+        owner = metadata_program::id(),
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateCandyMachineMint<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// CHECK: This is the key the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: This is the Candy Machine v3 account being minted from
+    #[account(owner = candy_machine_program::id())]
+    pub candy_machine: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the SlotHashes sysvar Candy Machine seeds its selection with
+    #[account(address = solana_program::sysvar::slot_hashes::ID)]
+    pub recent_slothashes: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTraitTable<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump,
+        has_one = authority,
+    )]
+    pub state: Account<'info, RarityState>,
+    
     #[account(
         init,
         payer = authority,
-        space = 8 + std::mem::size_of::<RarityState>()*10,
-        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        space = TRAIT_TABLE_SIZE,
+        seeds = [b"trait-table", state.key().as_ref()],
         bump
     )]
-    pub state: Account<'info, RarityState>,
+    pub trait_table: Account<'info, TraitTable>,
     
-    /// CHECK: This is the merkle tree we're associating with our rarity state
+    /// CHECK: This is the merkle tree the rarity state is associated with
     pub merkle_tree: UncheckedAccount<'info>,
     
-    /// CHECK: We need this to read the max depth and buffer size
-    pub merkle_tree_account: UncheckedAccount<'info>,
-    
     #[account(mut)]
     pub authority: Signer<'info>,
-
-    #[account(mut, address=fee_receiver::id())]
-    pub fee_receiver: AccountInfo<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateRarityData<'info> {
+#[instruction(clear_existing: bool, traits: Vec<TraitFrequency>)]
+pub struct UploadTraitFrequencies<'info> {
     #[account(
-        mut,
-        realloc = std::mem::size_of::<RarityState>() + state.to_account_info().data_len(),
-        realloc::zero = false,
-        realloc::payer = authority,
         seeds = [b"nft-beater", merkle_tree.key().as_ref()],
-        bump,
+        bump = state.bump,
         has_one = authority,
     )]
     pub state: Account<'info, RarityState>,
     
+    #[account(
+        mut,
+        realloc = trait_table.space_after_upload(clear_existing, &traits),
+        realloc::zero = false,
+        realloc::payer = authority,
+        seeds = [b"trait-table", state.key().as_ref()],
+        bump = trait_table.bump,
+    )]
+    pub trait_table: Account<'info, TraitTable>,
+    
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
     #[account(mut)]
     pub authority: Signer<'info>,
     
-    #[account(mut)]
-    pub merkle_tree: AccountInfo<'info>,
-
     #[account(mut, address=fee_receiver::id())]
     pub fee_receiver: AccountInfo<'info>,
     
@@ -642,85 +2549,155 @@ pub struct UpdateRarityData<'info> {
 }
 
 #[derive(Accounts)]
-pub struct ValidateMint<'info> {
+pub struct ValidateCoreAttributes<'info> {
     #[account(
-        seeds = [b"nft-beater", merkle_tree.to_account_info().key().as_ref()],
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
         bump = state.bump
     )]
     pub state: Account<'info, RarityState>,
     
-    /// The merkle tree account
-    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"trait-table", state.key().as_ref()],
+        bump = trait_table.bump,
+    )]
+    pub trait_table: Account<'info, TraitTable>,
     
+    /// CHECK: This is the merkle tree the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
     
+    /// CHECK: This is the transaction sender/minter
     #[account(mut)]
     pub minter: Signer<'info>,
-
-    #[account(mut, address=fee_receiver::id())] 
+    
+    /// CHECK: This is the MPL Core asset being scored
+    #[account(owner = mpl_core_program::id())]
+    pub asset_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
     pub fee_receiver: AccountInfo<'info>,
-
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AnalyzeMintTransaction<'info> {
+pub struct ValidateToken2022<'info> {
     #[account(
-        mut,
-        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
         bump = state.bump
     )]
     pub state: Account<'info, RarityState>,
     
-    /// The merkle tree account
+    /// CHECK: This is the key the rarity state is associated with
     pub merkle_tree: UncheckedAccount<'info>,
     
-    /// The tree config account to get the current mint count
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: This is the Token-2022 mint carrying the token-metadata extension
+    #[account(owner = spl_token_2022::id())]
+    pub mint_account: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateToken2022Traits<'info> {
     #[account(
-        seeds = [merkle_tree.key().as_ref()],
-        bump,
-        seeds::program = bubblegum_program::id(),
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
     )]
-    pub tree_config: Account<'info, TreeConfig>,
+    pub state: Account<'info, RarityState>,
     
-    /// CHECK: This account is the mint account from the transaction we're analyzing
+    #[account(
+        seeds = [b"trait-table", state.key().as_ref()],
+        bump = trait_table.bump,
+    )]
+    pub trait_table: Account<'info, TraitTable>,
+    
+    /// CHECK: This is the key the rarity state is associated with
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    /// CHECK: This is the transaction sender/minter
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    
+    /// CHECK: This is the Token-2022 mint carrying the token-metadata extension
+    #[account(owner = spl_token_2022::id())]
     pub mint_account: UncheckedAccount<'info>,
     
-    /// CHECK: This account is the metadata account for the mint
-    pub metadata_account: UncheckedAccount<'info>,
+    /// CHECK: This is the fee receiver
+    #[account(mut, address=fee_receiver::id())]
+    pub fee_receiver: AccountInfo<'info>,
     
-    /// CHECK: This is the transaction sender
-    pub transaction_sender: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(program_id: Pubkey, instructions: Vec<AdapterInstruction>)]
+pub struct RegisterLaunchpadAdapter<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = LaunchpadAdapter::space(&instructions),
+        seeds = [b"launchpad-adapter", program_id.as_ref()],
+        bump
+    )]
+    pub launchpad_adapter: Account<'info, LaunchpadAdapter>,
     
-    /// CHECK: This is the sysvar instructions account
-    pub instructions_sysvar: UncheckedAccount<'info>,
+    #[account(mut, address = fee_receiver::id())]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct GetMintStatistics<'info> {
+#[instruction(instructions: Vec<AdapterInstruction>)]
+pub struct UpdateLaunchpadAdapter<'info> {
     #[account(
-        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
-        bump = state.bump
+        mut,
+        realloc = LaunchpadAdapter::space(&instructions),
+        realloc::zero = false,
+        realloc::payer = admin,
+        seeds = [b"launchpad-adapter", launchpad_adapter.program_id.as_ref()],
+        bump = launchpad_adapter.bump,
     )]
-    pub state: Account<'info, RarityState>,
+    pub launchpad_adapter: Account<'info, LaunchpadAdapter>,
+    
+    #[account(mut, address = fee_receiver::id())]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ValidateMintCore<'info> {
+pub struct ValidateLaunchpadMint<'info> {
     #[account(
-        seeds = [b"nft-beater", merkle_tree.to_account_info().key().as_ref()],
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
         bump = state.bump
     )]
     pub state: Account<'info, RarityState>,
     
-    /// The merkle tree account
+    /// CHECK: This is the key the rarity state is associated with
     pub merkle_tree: UncheckedAccount<'info>,
     
+    #[account(
+        seeds = [b"launchpad-adapter", launchpad_adapter.program_id.as_ref()],
+        bump = launchpad_adapter.bump,
+    )]
+    pub launchpad_adapter: Account<'info, LaunchpadAdapter>,
+    
     /// CHECK: This is the transaction sender/minter
     #[account(mut)]
     pub minter: Signer<'info>,
     
-    /// CHECK: Optional asset account if known
-    pub asset_account: UncheckedAccount<'info>,
+    /// CHECK: Minted asset, required when the adapter locates the index through an asset account
+    pub asset_account: Option<UncheckedAccount<'info>>,
     
     /// CHECK: This is the fee receiver
     #[account(mut, address=fee_receiver::id())]
@@ -734,6 +2711,15 @@ pub struct ValidateMintCore<'info> {
 
 #[derive(Accounts)]
 pub struct DebugMintInstruction<'info> {
+    #[account(
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
+        bump = state.bump
+    )]
+    pub state: Account<'info, RarityState>,
+    
+    /// The merkle tree account
+    pub merkle_tree: UncheckedAccount<'info>,
+    
     #[account(mut)]
     pub authority: Signer<'info>,
     
@@ -744,7 +2730,7 @@ pub struct DebugMintInstruction<'info> {
 #[derive(Accounts)]
 pub struct ValidateMintFromLogs<'info> {
     #[account(
-        seeds = [b"nft-beater", state.merkle_tree.key().as_ref()],
+        seeds = [b"nft-beater", merkle_tree.key().as_ref()],
         bump = state.bump
     )]
     pub state: Account<'info, RarityState>,
@@ -764,6 +2750,16 @@ pub struct ValidateMintFromLogs<'info> {
     pub instructions_sysvar: UncheckedAccount<'info>,
     
     pub system_program: Program<'info, System>,
+    
+    // Optional accounts follow the original ones so existing callers can omit them
+    #[account(
+        seeds = [b"launchpad-adapter", launchpad_adapter.program_id.as_ref()],
+        bump = launchpad_adapter.bump,
+    )]
+    pub launchpad_adapter: Option<Account<'info, LaunchpadAdapter>>,
+    
+    /// CHECK: Minted asset, required when the adapter locates the index through an asset account
+    pub asset_account: Option<UncheckedAccount<'info>>,
 }
 
 #[error_code]
@@ -782,32 +2778,514 @@ pub enum ErrorCode {
     
     #[msg("Mint transaction analysis failed")]
     MintTransactionAnalysisFailed,
+    
+    #[msg("Invalid URI template")]
+    InvalidUriTemplate,
+    
+    #[msg("No URI template configured")]
+    UriTemplateNotConfigured,
+    
+    #[msg("URI does not match the collection's template")]
+    UriTemplateMismatch,
+    
+    #[msg("Invalid name template")]
+    InvalidNameTemplate,
+    
+    #[msg("Name does not match the collection's template")]
+    NameTemplateMismatch,
+    
+    #[msg("Asset has no attributes")]
+    NoAttributes,
+    
+    #[msg("Trait not found in trait table")]
+    UnknownTrait,
+    
+    #[msg("Trait count exceeds total supply")]
+    InvalidTraitFrequency,
+    
+    #[msg("Asset update authority does not match the trait table")]
+    UpdateAuthorityMismatch,
+    
+    #[msg("Invalid Candy Machine account")]
+    InvalidCandyMachine,
+    
+    #[msg("Candy Machine has no items left")]
+    CandyMachineEmpty,
+    
+    #[msg("Candy Machine config lines are not fully loaded")]
+    CandyMachineNotFullyLoaded,
+    
+    #[msg("Invalid Token Metadata account")]
+    InvalidMetadataAccount,
+    
+    #[msg("Invalid Token-2022 mint or missing token-metadata extension")]
+    InvalidToken2022Mint,
+    
+    #[msg("Invalid launchpad adapter")]
+    InvalidLaunchpadAdapter,
+    
+    #[msg("Invalid batch size")]
+    InvalidBatchSize,
+    
+    #[msg("Invalid mint history capacity")]
+    InvalidMintHistoryCapacity,
+    
+    #[msg("Mint already recorded")]
+    MintAlreadyRecorded,
+    
+    #[msg("Invalid rank width")]
+    InvalidRankWidth,
+    
+    #[msg("Invalid rank")]
+    InvalidRank,
+    
+    #[msg("NFT rank does not meet the requirement")]
+    RankNotMet,
+    
+    #[msg("Invalid score width")]
+    InvalidScoreWidth,
+    
+    #[msg("Invalid rarity score")]
+    InvalidScore,
+    
+    #[msg("Rarity score of this index is unknown")]
+    RarityUnknown,
+    
+    #[msg("Invalid rarity payload")]
+    InvalidRarityPayload,
+    
+    #[msg("Asset is not part of the state's collection")]
+    CollectionMismatch,
+    
+    #[msg("Minter did not pay for or receive the mint")]
+    MinterMismatch,
+    
+    #[msg("Invalid minter statistics account")]
+    InvalidMinterStats,
+    
+    #[msg("Account cannot grow that much in one instruction")]
+    AccountGrowthTooLarge,
+}
+
+// Helper to extract the NFT index from a name and URI and look up its rarity
+fn score_by_index(state: &RarityState, name: &str, uri: &str) -> Result<(Option<u64>, u16)> {
+    let index = extract_index(state, name, uri)?;
+    score_index(state, index)
+}
+
+// Helper to look up the rarity of a resolved NFT index
+fn score_index(state: &RarityState, nft_index: u64) -> Result<(Option<u64>, u16)> {
+    msg!("Found NFT index: {}", nft_index);
+    let rarity = rarity_at(state, nft_index)?;
+    msg!("NFT at index {} has rarity score: {}", nft_index, rarity);
+    Ok((Some(nft_index), rarity))
+}
+
+// Helper to score an MPL Core asset by the index in its name or URI
+fn score_core_asset(state: &RarityState, asset_account: &AccountInfo) -> Result<(Option<u64>, u16)> {
+    let base_asset = BaseAssetV1::deserialize(&mut &asset_account.try_borrow_data()?[..])?;
+    msg!("Name: {}", base_asset.name);
+    msg!("URI: {}", base_asset.uri);
+    check_core_collection(state, &base_asset, &asset_account.key())?;
+    
+    // Extract the index using the collection's configured template
+    score_by_index(state, &base_asset.name, &base_asset.uri)
+}
+
+// Helper to score the closest earlier Bubblegum mint into the state's tree
+fn score_bubblegum_mint(
+    state: &RarityState,
+    ix_sysvar: &AccountInfo,
+    merkle_tree: &Pubkey,
+) -> Result<(Option<u64>, u16)> {
+    let (_, metadata) = find_bubblegum_mint(ix_sysvar, merkle_tree)?;
+    msg!("Name: {}", metadata.name);
+    msg!("URI: {}", metadata.uri);
+    
+    // Extract the index using the collection's configured template
+    score_by_index(state, &metadata.name, &metadata.uri)
+}
+
+// Helper to score a legacy Token Metadata NFT by the index in its name or URI
+fn score_token_metadata(
+    state: &RarityState,
+    metadata_account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<(Option<u64>, u16)> {
+    let metadata = Metadata::safe_deserialize(&metadata_account.try_borrow_data()?).map_err(|_| {
+        msg!("Failed to deserialize Token Metadata account");
+        error!(ErrorCode::InvalidMetadataAccount)
+    })?;
+    if metadata.mint != *mint {
+        msg!("Metadata belongs to mint {}", metadata.mint);
+        return Err(error!(ErrorCode::InvalidMetadataAccount));
+    }
+    msg!("Name: {}", metadata.name);
+    msg!("URI: {}", metadata.uri);
+    
+    // Extract the index using the collection's configured template
+    score_by_index(state, &metadata.name, &metadata.uri)
+}
+
+// Helper to score the config line the next Candy Machine v3 mint receives
+fn score_candy_machine_mint(
+    state: &RarityState,
+    candy_machine: &AccountInfo,
+    recent_slothashes: &AccountInfo,
+) -> Result<(Option<u64>, u16)> {
+    let candy_machine_data = candy_machine.try_borrow_data()?;
+    let candy_machine = load_candy_machine(&candy_machine_data)?;
+    msg!(
+        "Candy Machine has redeemed {} of {} items",
+        candy_machine.items_redeemed,
+        candy_machine.data.items_available
+    );
+    
+    let slot_hashes = recent_slothashes.try_borrow_data()?;
+    let clock = Clock::get()?;
+    let config_line = predict_next_config_line(
+        &candy_machine,
+        &candy_machine_data,
+        &slot_hashes,
+        clock.unix_timestamp,
+    )?;
+    msg!("Name: {}", config_line.name);
+    msg!("URI: {}", config_line.uri);
+    
+    // Extract the index using the collection's configured template
+    score_by_index(state, &config_line.name, &config_line.uri)
+}
+
+// Helper to score an MPL Core asset by the traits in its Attributes plugin
+fn score_core_attributes(
+    state: &RarityState,
+    trait_table: &TraitTable,
+    asset_account: &AccountInfo,
+) -> Result<(Option<u64>, u16)> {
+    let base_asset = BaseAssetV1::deserialize(&mut &asset_account.try_borrow_data()?[..])?;
+    msg!("Name: {}", base_asset.name);
+    
+    // Only score assets from the collection the trait table was built for
+    let asset_update_authority = match base_asset.update_authority {
+        UpdateAuthority::Address(address) => address,
+        UpdateAuthority::Collection(collection) => collection,
+        UpdateAuthority::None => {
+            msg!("Asset has no update authority");
+            return Err(error!(ErrorCode::UpdateAuthorityMismatch));
+        }
+    };
+    if asset_update_authority != trait_table.update_authority {
+        msg!("Asset update authority {} does not match {}", asset_update_authority, trait_table.update_authority);
+        return Err(error!(ErrorCode::UpdateAuthorityMismatch));
+    }
+    
+    // Read the trait list from the asset's Attributes plugin
+    let (_, attributes, _) = mpl_core::fetch_plugin::<BaseAssetV1, Attributes>(
+        asset_account,
+        PluginType::Attributes,
+    ).map_err(|_| {
+        msg!("Asset has no Attributes plugin");
+        error!(ErrorCode::NoAttributes)
+    })?;
+    
+    let rarity = statistical_rarity_score(
+        trait_table,
+        attributes.attribute_list.iter().map(|a| (a.key.as_str(), a.value.as_str())),
+        state.max_score(),
+    )?;
+    msg!("Asset {} has statistical rarity score: {}", asset_account.key(), rarity);
+    Ok((None, rarity))
+}
+
+// Helper to score a Token-2022 NFT by the index in its token-metadata name or URI
+fn score_token_2022(state: &RarityState, mint_account: &AccountInfo) -> Result<(Option<u64>, u16)> {
+    let token_metadata = load_token_metadata(mint_account)?;
+    msg!("Name: {}", token_metadata.name);
+    msg!("URI: {}", token_metadata.uri);
+    
+    // Extract the index using the collection's configured template
+    score_by_index(state, &token_metadata.name, &token_metadata.uri)
+}
+
+// Helper to score a Token-2022 NFT by its additional metadata key/value pairs
+fn score_token_2022_traits(
+    state: &RarityState,
+    trait_table: &TraitTable,
+    mint_account: &AccountInfo,
+) -> Result<(Option<u64>, u16)> {
+    let token_metadata = load_token_metadata(mint_account)?;
+    msg!("Name: {}", token_metadata.name);
+    
+    // Only score mints from the collection the trait table was built for
+    let update_authority = match Option::<Pubkey>::from(token_metadata.update_authority) {
+        Some(update_authority) => update_authority,
+        None => {
+            msg!("Mint has no update authority");
+            return Err(error!(ErrorCode::UpdateAuthorityMismatch));
+        }
+    };
+    if update_authority != trait_table.update_authority {
+        msg!("Mint update authority {} does not match {}", update_authority, trait_table.update_authority);
+        return Err(error!(ErrorCode::UpdateAuthorityMismatch));
+    }
+    
+    let rarity = statistical_rarity_score(
+        trait_table,
+        token_metadata.additional_metadata.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+        state.max_score(),
+    )?;
+    msg!("Mint {} has statistical rarity score: {}", mint_account.key(), rarity);
+    Ok((None, rarity))
+}
+
+// Helper to score the NFT minted by the closest earlier instruction a launchpad adapter recognizes
+fn score_launchpad_mint(
+    state: &RarityState,
+    launchpad_adapter: &LaunchpadAdapter,
+    ix_sysvar: &AccountInfo,
+    asset_account: Option<&AccountInfo>,
+    asset_id: &mut Pubkey,
+) -> Result<(Option<u64>, u16)> {
+    let (nft_index, asset) = launchpad_mint_index(state, launchpad_adapter, ix_sysvar, asset_account)?;
+    *asset_id = asset;
+    score_index(state, nft_index)
+}
+
+// Helper to score a launchpad mint for extract_nft_index_from_logs, which must be called directly
+fn score_mint_from_logs(
+    state: &RarityState,
+    launchpad_adapter: Option<&LaunchpadAdapter>,
+    ix_sysvar: &AccountInfo,
+    asset_account: Option<&AccountInfo>,
+    asset_id: &mut Pubkey,
+) -> Result<(Option<u64>, u16)> {
+    // For security, we need to verify that the instruction we're analyzing
+    // is actually from our program
+    let current_ix = get_instruction_relative(0, ix_sysvar)?;
+    if current_ix.program_id != crate::id() {
+        msg!("Current instruction is not from our program");
+        return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+    }
+    
+    let (nft_index, asset) = match launchpad_adapter {
+        Some(launchpad_adapter) => launchpad_mint_index(state, launchpad_adapter, ix_sysvar, asset_account)?,
+        None => {
+            // Look for the Menagerie mint instruction (previous instruction)
+            let menagerie_ix = get_instruction_relative(-1, ix_sysvar)?;
+            if menagerie_ix.program_id != menagerie_program::id() {
+                msg!("Previous instruction is not from Menagerie program");
+                return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+            }
+            resolve_menagerie_index(state, &menagerie_ix, asset_account)?
+        }
+    };
+    *asset_id = asset;
+    
+    // Build and log the expected URI
+    if let Some(uri) = format_uri(&state.uri_template, nft_index) {
+        msg!("Expected URI: {}", uri);
+    }
+    
+    score_index(state, nft_index)
 }
 
-// Helper function to validate NFT rarity
-fn validate_nft_rarity(
+// Helper to turn a scored NFT into the validation result. Normal runs fail when scoring
+// fails or the score is below the threshold, so they only ever return passed results;
+// dry runs report the outcome either way.
+fn finish_validation(
     state: &Account<RarityState>,
-    nft_index: u64,
-    min_rarity_percentage: u8,
-) -> Result<()> {
-    // Ensure the index is within bounds
-    if nft_index >= state.rarity_map.len() as u64 {
-        msg!("NFT index {} is out of bounds", nft_index);
-        return Err(error!(ErrorCode::IndexOutOfBounds));
+    scored: Result<(Option<u64>, u16)>,
+    min_score: u16,
+    asset_id: Pubkey,
+    minter: Pubkey,
+    remaining_accounts: &[AccountInfo],
+    dry_run: bool,
+) -> Result<RarityResult> {
+    let (result, error) = match scored {
+        Ok((index, rarity)) => (RarityResult::new(state, index, rarity, min_score, asset_id), None),
+        Err(err) => {
+            msg!("NFT could not be scored: {}", err);
+            (RarityResult { index: None, score: 0, tier: 0, passed: false, asset_id }, Some(err))
+        }
+    };
+    
+    if dry_run {
+        msg!(
+            "Dry run: score {} tier {} {} threshold {}",
+            result.score,
+            result.tier,
+            if result.passed { "meets" } else { "misses" },
+            min_score
+        );
+        emit!(ValidationPreviewed {
+            state: state.key(),
+            asset_id: result.asset_id,
+            index: result.index,
+            score: result.score,
+            tier: result.tier,
+            threshold: min_score,
+            passed: result.passed,
+            minter,
+            error_code: error.as_ref().map(program_error_code),
+        });
+        return Ok(result);
     }
     
-    // Get the rarity score
-    let rarity = state.rarity_map[nft_index as usize];
-    msg!("NFT at index {} has rarity score: {}", nft_index, rarity);
+    if result.passed {
+        msg!("Validation successful: NFT meets rarity threshold");
+        let minter_stats = find_minter_stats(remaining_accounts, &state.key(), &minter);
+        update_minter_stats(minter_stats, |stats| {
+            record_validation(stats, true, state.score_percent(result.score), FEE_LAMPORTS)
+        })?;
+        emit!(ValidationPassed {
+            state: state.key(),
+            asset_id: result.asset_id,
+            index: result.index,
+            score: result.score,
+            threshold: min_score,
+            minter,
+        });
+        return Ok(result);
+    }
+    
+    // Report the failure before aborting; the event stays in the failed transaction's logs
+    let (err, error_code) = match error {
+        Some(err) => {
+            let error_code = program_error_code(&err);
+            (err, Some(error_code))
+        }
+        None => {
+            msg!("Rarity {} below threshold {}", result.score, min_score);
+            (error!(ErrorCode::RarityBelowThreshold), None)
+        }
+    };
+    emit!(ValidationFailed {
+        state: state.key(),
+        asset_id: result.asset_id,
+        index: result.index,
+        score: result.score,
+        threshold: min_score,
+        minter,
+        error_code,
+    });
+    Err(err)
+}
+
+// Helper to get the numeric code an error is reported with on chain
+fn program_error_code(err: &Error) -> u64 {
+    match err {
+        Error::AnchorError(anchor_error) => anchor_error.error_code_number as u64,
+        Error::ProgramError(program_error) => u64::from(program_error.program_error.clone()),
+    }
+}
+
+// Helper to resolve the NFT index minted by the closest earlier instruction a launchpad
+// adapter recognizes, along with the minted asset when the adapter locates it through one
+fn launchpad_mint_index(
+    state: &RarityState,
+    launchpad_adapter: &LaunchpadAdapter,
+    ix_sysvar: &AccountInfo,
+    asset_account: Option<&AccountInfo>,
+) -> Result<(u64, Pubkey)> {
+    let (ix, adapter_instruction) = find_adapter_instruction(ix_sysvar, launchpad_adapter)?;
+    
+    match adapter_instruction.location {
+        IndexLocation::InstructionData { offset, width } => {
+            Ok((read_index_from_data(&ix.data, offset, width)?, Pubkey::default()))
+        }
+        IndexLocation::AssetAccount { account_index } => {
+            let asset_key = match ix.accounts.get(account_index as usize) {
+                Some(meta) => meta.pubkey,
+                None => {
+                    msg!("Launchpad instruction has no account {}", account_index);
+                    return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+                }
+            };
+            
+            // The minted asset must be passed in and owned by MPL Core
+            let asset_account = match asset_account {
+                Some(asset_account) if asset_account.key() == asset_key => asset_account,
+                _ => {
+                    msg!("Asset account {} must be provided", asset_key);
+                    return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+                }
+            };
+            if *asset_account.owner != mpl_core_program::id() {
+                msg!("Asset account {} is not an MPL Core asset", asset_key);
+                return Err(error!(ErrorCode::MintTransactionAnalysisFailed));
+            }
+            
+            let base_asset = BaseAssetV1::deserialize(&mut &asset_account.try_borrow_data()?[..])?;
+            msg!("Name: {}", base_asset.name);
+            msg!("URI: {}", base_asset.uri);
+            Ok((extract_index(state, &base_asset.name, &base_asset.uri)?, asset_key))
+        }
+    }
+}
+
+// Helper to resolve the NFT index minted by a Menagerie instruction from what is visible on chain,
+// along with the created asset when the index comes from it
+fn resolve_menagerie_index(
+    state: &RarityState,
+    menagerie_ix: &Instruction,
+    asset_account: Option<&AccountInfo>,
+) -> Result<(u64, Pubkey)> {
+    // The created Core asset is authoritative, as long as the mint actually touched it
+    if let Some(asset_account) = asset_account {
+        let in_mint = menagerie_ix.accounts.iter().any(|meta| meta.pubkey == asset_account.key());
+        if in_mint && *asset_account.owner == mpl_core_program::id() {
+            let data = asset_account.try_borrow_data()?;
+            match BaseAssetV1::deserialize(&mut &data[..]) {
+                Ok(base_asset) => {
+                    check_core_collection(state, &base_asset, &asset_account.key())?;
+                    match extract_index(state, &base_asset.name, &base_asset.uri) {
+                        Ok(index) => {
+                            msg!("Using NFT index from created asset: {}", index);
+                            return Ok((index, asset_account.key()));
+                        }
+                        Err(_) => msg!("Created asset URI {} does not match the template", base_asset.uri),
+                    }
+                }
+                Err(_) => msg!("Failed to deserialize created asset"),
+            }
+        } else {
+            msg!("Asset account {} was not created by the Menagerie mint", asset_account.key());
+        }
+    }
     
-    // Check against threshold
-    if rarity < min_rarity_percentage {
-        msg!("Rarity {} below threshold {}", rarity, min_rarity_percentage);
-        return Err(error!(ErrorCode::RarityBelowThreshold));
+    // Fall back to the index encoded in the MintCore/MintCv3 instruction data
+    if let Some(index) = decode_menagerie_index(menagerie_ix) {
+        msg!("Using NFT index from instruction data: {}", index);
+        return Ok((index, Pubkey::default()));
     }
     
-    msg!("NFT meets rarity threshold: {} >= {}", rarity, min_rarity_percentage);
-    Ok(())
+    // Finally, use the index Menagerie returned, if any
+    if let Some((program_id, data)) = get_return_data() {
+        if program_id == menagerie_program::id() && data.len() >= 8 {
+            let index = u64::from_le_bytes(data[0..8].try_into().unwrap());
+            msg!("Using NFT index from return data: {}", index);
+            return Ok((index, Pubkey::default()));
+        }
+    }
+    
+    msg!("Could not determine the minted NFT index");
+    Err(error!(ErrorCode::MintTransactionAnalysisFailed))
+}
+
+// Helper to decode the NFT index following the discriminator of MintCore/MintCv3 instructions
+fn decode_menagerie_index(ix: &Instruction) -> Option<u64> {
+    let data = &ix.data;
+    if data.len() < 16 {
+        return None;
+    }
+    
+    let discriminator = &data[0..8];
+    if discriminator != menagerie_program::MINT_CORE_DISCRIMINATOR &&
+       discriminator != menagerie_program::MINT_CV3_DISCRIMINATOR {
+        return None;
+    }
+    
+    Some(u64::from_le_bytes(data[8..16].try_into().unwrap()))
 }
 
 // Helper function to analyze Menagerie instruction data
@@ -887,8 +3365,8 @@ fn analyze_menagerie_instruction(ix: &Instruction) -> Result<()> {
     
     // Print account keys
     msg!("Instruction has {} account keys:", ix.accounts.len());
-    for (i, key) in ix.accounts.iter().enumerate() {
-        msg!("Account #{}: {}", i, ix.program_id);
+    for (i, meta) in ix.accounts.iter().enumerate() {
+        msg!("Account #{}: {}", i, meta.pubkey);
     }
     
     Ok(())
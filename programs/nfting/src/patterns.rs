@@ -0,0 +1,101 @@
+use crate::state::{MintPattern, MAX_MINT_PATTERNS};
+
+// Difference between the indices of two consecutive mints. When the collection size is
+// known the difference wraps around it, so sequential drops read as 1 and strided ones
+// as their stride even when the index wraps.
+pub fn index_difference(previous: u64, current: u64, collection_size: u64) -> u64 {
+    if collection_size == 0 || previous >= collection_size || current >= collection_size {
+        return current.abs_diff(previous);
+    }
+
+    (current + collection_size - previous) % collection_size
+}
+
+// Add one observation of `difference` to the pattern histogram and refresh every
+// bucket's probability. Once all buckets are taken, the least observed one is reused
+// for the new difference and keeps its count (Space-Saving), so frequent patterns are
+// never lost and counts only overestimate by at most the smallest bucket.
+pub fn record_difference(patterns: &mut Vec<MintPattern>, difference: u64) {
+    if let Some(pattern) = patterns.iter_mut().find(|p| p.difference == difference) {
+        pattern.occurrences += 1;
+    } else if patterns.len() < MAX_MINT_PATTERNS {
+        patterns.push(MintPattern {
+            difference,
+            occurrences: 1,
+            probability: 0.0,
+        });
+    } else if let Some(pattern) = patterns.iter_mut().min_by_key(|p| p.occurrences) {
+        pattern.difference = difference;
+        pattern.occurrences += 1;
+    }
+
+    let total_occurrences: u64 = patterns.iter().map(|p| p.occurrences).sum();
+    for pattern in patterns.iter_mut() {
+        pattern.probability = pattern.occurrences as f64 / total_occurrences as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occurrences(patterns: &[MintPattern], difference: u64) -> Option<u64> {
+        patterns.iter().find(|p| p.difference == difference).map(|p| p.occurrences)
+    }
+
+    #[test]
+    fn differences_wrap_around_the_collection() {
+        assert_eq!(index_difference(4, 5, 10), 1);
+        assert_eq!(index_difference(9, 0, 10), 1);
+        assert_eq!(index_difference(8, 1, 10), 3);
+        assert_eq!(index_difference(5, 5, 10), 0);
+        
+        // Without a collection size, or with indices outside it, the plain distance is used
+        assert_eq!(index_difference(9, 0, 0), 9);
+        assert_eq!(index_difference(12, 3, 10), 9);
+    }
+
+    #[test]
+    fn repeated_differences_share_a_bucket() {
+        let mut patterns = Vec::new();
+        record_difference(&mut patterns, 1);
+        record_difference(&mut patterns, 1);
+        record_difference(&mut patterns, 7);
+        
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(occurrences(&patterns, 1), Some(2));
+        assert_eq!(occurrences(&patterns, 7), Some(1));
+        assert!((patterns[0].probability - 2.0 / 3.0).abs() < 1e-9);
+        assert!((patterns.iter().map(|p| p.probability).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn full_histogram_reuses_the_least_observed_bucket() {
+        let mut patterns = Vec::new();
+        for difference in 0..MAX_MINT_PATTERNS as u64 {
+            record_difference(&mut patterns, difference);
+            record_difference(&mut patterns, difference);
+        }
+        // Difference 3 becomes the only bucket with the most observations
+        record_difference(&mut patterns, 3);
+        
+        // Every bucket but 3 ties at 2; the first of them (difference 0) is reused and
+        // the new difference inherits its count
+        record_difference(&mut patterns, 1_000);
+        assert_eq!(patterns.len(), MAX_MINT_PATTERNS);
+        assert_eq!(occurrences(&patterns, 0), None);
+        assert_eq!(occurrences(&patterns, 1_000), Some(3));
+        assert_eq!(occurrences(&patterns, 3), Some(3));
+        
+        // The next new difference replaces the least observed bucket again, never the frequent ones
+        record_difference(&mut patterns, 2_000);
+        assert_eq!(occurrences(&patterns, 1), None);
+        assert_eq!(occurrences(&patterns, 2_000), Some(3));
+        assert_eq!(occurrences(&patterns, 3), Some(3));
+        assert_eq!(occurrences(&patterns, 1_000), Some(3));
+        
+        let total: u64 = patterns.iter().map(|p| p.occurrences).sum();
+        assert_eq!(total, 2 * MAX_MINT_PATTERNS as u64 + 3);
+        assert!((patterns.iter().map(|p| p.probability).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,180 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{MintPattern, MinterRank, RarityState};
+
+// Outcome of a rarity validation, returned to callers through return data so
+// other programs can branch on it after a CPI
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RarityResult {
+    // NFT index the asset resolved to (None when scored from traits)
+    pub index: Option<u64>,
+
+    // Rarity score of the asset (0-100, or 0-10000 basis points for states with u16 scores)
+    pub score: u16,
+
+    // Rarity tier: number of the state's thresholds the score meets (0 = Common)
+    pub tier: u8,
+
+    // Whether the score met the requested minimum. Only informative for dry runs: a normal
+    // run that misses the minimum fails the transaction, so its result always has this set
+    pub passed: bool,
+
+    // Asset ID or mint of the NFT, when known (default pubkey otherwise)
+    pub asset_id: Pubkey,
+}
+
+impl RarityResult {
+    pub fn new(
+        state: &RarityState,
+        index: Option<u64>,
+        score: u16,
+        min_score: u16,
+        asset_id: Pubkey,
+    ) -> Self {
+        RarityResult {
+            index,
+            score,
+            tier: rarity_tier(&state.thresholds(), score),
+            passed: score >= min_score,
+            asset_id,
+        }
+    }
+}
+
+// Asset ID and rarity a Bubblegum nonce maps to, returned by predict_index
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct IndexPrediction {
+    // Asset ID Bubblegum assigns at the nonce
+    pub asset_id: Pubkey,
+
+    // Rarity map index the asset ID maps to
+    pub index: u64,
+
+    // Rarity score at that index, in the state's score unit
+    pub score: u16,
+
+    // Rarity tier of that score
+    pub tier: u8,
+}
+
+// Outcome of validate_batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BatchResult {
+    // Bit i (LSB first within each byte) is set when item i passed
+    pub passed_bitmap: Vec<u8>,
+
+    // Bit i is set when item i could not be scored (its index could not be resolved or has
+    // no rarity data), so its score and pass bit say nothing about its rarity
+    pub unscored_bitmap: Vec<u8>,
+
+    // Rarity score of each item (0 when the item could not be scored)
+    pub scores: Vec<u16>,
+
+    // Number of items that passed
+    pub passed_count: u32,
+
+    // Number of items that could not be scored
+    pub unscored_count: u32,
+}
+
+// Summary of a rarity state's recorded mints, returned by get_mint_statistics
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintStatistics {
+    // Total number of mints recorded
+    pub total_mints: u64,
+
+    // Number of records in the mint history the counts below cover
+    pub records_in_history: u32,
+
+    // Number of those records with a known rarity score
+    pub records_with_rarity: u32,
+
+    // Records per rarity tier (index 0 = Common)
+    pub tier_counts: Vec<u32>,
+
+    // Most frequent index differences between consecutive mints
+    pub top_patterns: Vec<MintPattern>,
+
+    // Minters with the most recorded mints
+    pub top_minters: Vec<MinterRank>,
+}
+
+// Shape of a rarity map and the chance a mint meets a threshold, returned by get_rarity_distribution
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RarityDistribution {
+    // Number of entries in the rarity map
+    pub total: u32,
+
+    // Entries with a known score; the counts and chances below are out of these
+    pub known: u32,
+
+    // Entries per score from 0 to 100 (scores above 100 count towards 100)
+    pub histogram: Vec<u32>,
+
+    // Entries per rarity tier (index 0 = Common)
+    pub tier_counts: Vec<u32>,
+
+    // Minimum score the estimate below was computed for, in the state's score unit
+    pub threshold: u16,
+
+    // Entries meeting the threshold
+    pub passing: u32,
+
+    // Chance that a uniformly selected scored index meets the threshold, in basis points
+    pub pass_probability_bps: u16,
+
+    // Chance that a uniformly selected scored index reaches each tier or a higher one, in basis points
+    pub tier_pass_probability_bps: Vec<u16>,
+}
+
+// Which part of a rarity map has scores, returned by get_rarity_coverage
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RarityCoverage {
+    // Number of entries in the rarity map
+    pub total: u32,
+
+    // Entries with a known score
+    pub known: u32,
+
+    // Ranges still missing a score, from the requested start index on
+    pub missing_ranges: Vec<IndexRange>,
+
+    // Index to pass as the start of the next query when more ranges are missing
+    pub next_index: Option<u32>,
+}
+
+// Half-open range of rarity map indices
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct IndexRange {
+    pub start_index: u32,
+
+    // One past the last index in the range
+    pub end_index: u32,
+}
+
+// Outcome of a rank validation, returned by validate_rank
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RankResult {
+    // NFT index the asset resolved to, if it could be resolved
+    pub index: Option<u64>,
+
+    // Rank of the NFT (1 = rarest, 0 when it could not be ranked)
+    pub rank: u32,
+
+    // Number of ranked items in the collection
+    pub total_ranked: u32,
+
+    // Share of the collection at or above this rank, in basis points
+    pub percentile_bps: u16,
+
+    // Whether the rank met the requirement
+    pub passed: bool,
+
+    // Asset ID of the NFT
+    pub asset_id: Pubkey,
+}
+
+// Tier of a score, counting the thresholds it meets (e.g. [50, 75, 90] and 80 gives tier 2)
+pub fn rarity_tier<T: PartialOrd>(thresholds: &[T], score: T) -> u8 {
+    thresholds.iter().filter(|threshold| score >= **threshold).count() as u8
+}
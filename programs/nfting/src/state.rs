@@ -15,7 +15,24 @@ pub const STATE_SIZE: usize = 8 + // Discriminator
     4 + // Vec length prefix for mint_records
     1000 * MINT_RECORD_SIZE + // mint_records: Vec<MintRecord> (reasonable max size)
     4 + // Vec length prefix for mint_patterns
-    100 * MINT_PATTERN_SIZE; // mint_patterns: Vec<MintPattern> (reasonable max size)
+    MAX_MINT_PATTERNS * MINT_PATTERN_SIZE + // mint_patterns: Vec<MintPattern> (reasonable max size)
+    4 + MAX_URI_LENGTH + 1 + 4 + MAX_URI_SUFFIX_LENGTH + 1 + 1 + // uri_template: UriTemplate
+    1 + // index_source: IndexSource
+    4 + MAX_NAME_LENGTH + 4 + // name_template: NameTemplate
+    4 + // Vec length prefix for top_minters
+    MAX_TOP_MINTERS * MINTER_RANK_SIZE + // top_minters: Vec<MinterRank>
+    4 + // Vec length prefix for score_histogram
+    SCORE_HISTOGRAM_BUCKETS * 4 + // score_histogram: Vec<u32>
+    1 + // score_width: u8
+    4 + // Vec length prefix for precise_thresholds
+    50; // precise_thresholds: Vec<u16> (reasonable max size)
+
+// Highest score of a state with u16 scores (basis points)
+pub const MAX_PRECISE_SCORE: u16 = 10_000;
+
+// Rarity map value marking an index whose score is unknown (u8::MAX, or u16::MAX for u16 scores)
+pub const UNKNOWN_SCORE: u8 = u8::MAX;
+pub const UNKNOWN_PRECISE_SCORE: u16 = u16::MAX;
 
 // Size of a MintRecord
 pub const MINT_RECORD_SIZE: usize = 
@@ -28,6 +45,86 @@ pub const MINT_RECORD_SIZE: usize =
     32 + // minter: Pubkey
     8; // timestamp: i64
 
+// Largest mint history ring buffer; the whole buffer is deserialized on every
+// write, so it has to fit in the program heap
+pub const MAX_MINT_HISTORY_CAPACITY: u32 = 200;
+
+// Size of a MintHistory account before any records are added
+pub const MINT_HISTORY_SIZE: usize = 8 + // Discriminator
+    32 + // state: Pubkey
+    1 + // bump
+    4 + // capacity: u32
+    4 + // head: u32
+    4; // Vec length prefix for records
+
+// Maximum number of distinct index differences tracked in mint_patterns
+pub const MAX_MINT_PATTERNS: usize = 100;
+
+// Size of a MintRecordAccount
+pub const MINT_RECORD_ACCOUNT_SIZE: usize = 8 + // Discriminator
+    32 + // state: Pubkey
+    1 + // bump
+    MINT_RECORD_SIZE; // record: MintRecord
+
+// One histogram bucket per score from 0 to 100
+pub const SCORE_HISTOGRAM_BUCKETS: usize = 101;
+
+// Number of minters kept on the state's leaderboard
+pub const MAX_TOP_MINTERS: usize = 10;
+
+// Size of a MinterRank
+pub const MINTER_RANK_SIZE: usize =
+    32 + // minter: Pubkey
+    8; // mints: u64
+
+// Size of a MinterStats account
+pub const MINTER_STATS_SIZE: usize = 8 + // Discriminator
+    32 + // state: Pubkey
+    32 + // minter: Pubkey
+    1 + // bump
+    8 + // mints: u64
+    8 + // validations_passed: u64
+    8 + // validations_failed: u64
+    1 + // best_score: u8
+    8; // fees_paid: u64
+
+// Maximum length of a metadata URI (matches Metaplex MAX_URI_LENGTH)
+pub const MAX_URI_LENGTH: usize = 200;
+
+// Maximum length of the suffix of a URI template
+pub const MAX_URI_SUFFIX_LENGTH: usize = 16;
+
+// Maximum length of an asset name (matches Metaplex MAX_NAME_LENGTH)
+pub const MAX_NAME_LENGTH: usize = 32;
+
+// Size of a TraitTable account before any trait frequencies are added
+pub const TRAIT_TABLE_SIZE: usize = 8 + // Discriminator
+    32 + // state: Pubkey
+    32 + // update_authority: Pubkey
+    1 + // bump
+    4 + // total_supply: u32
+    4; // Vec length prefix for traits
+
+// Size of a RankTable account before any ranks are uploaded
+pub const RANK_TABLE_SIZE: usize = 8 + // Discriminator
+    32 + // state: Pubkey
+    1 + // bump
+    1 + // rank_width: u8
+    4 + // total_ranked: u32
+    4; // Vec length prefix for ranks
+
+// Maximum number of instructions a launchpad adapter can recognize
+pub const MAX_ADAPTER_INSTRUCTIONS: usize = 16;
+
+// Maximum length of an instruction discriminator (Anchor uses 8 bytes, native programs 1)
+pub const MAX_DISCRIMINATOR_LENGTH: usize = 8;
+
+// Size of a LaunchpadAdapter account before any instructions are added
+pub const LAUNCHPAD_ADAPTER_SIZE: usize = 8 + // Discriminator
+    32 + // program_id: Pubkey
+    1 + // bump
+    4; // Vec length prefix for instructions
+
 // Size of a MintPattern
 pub const MINT_PATTERN_SIZE: usize = 
     8 + // difference: u64
@@ -35,6 +132,7 @@ pub const MINT_PATTERN_SIZE: usize =
     8; // probability: f64 (stored as bits)
 
 #[account]
+#[derive(Default)]
 pub struct RarityState {
     // The authority that can update this account
     pub authority: Pubkey,
@@ -54,17 +152,137 @@ pub struct RarityState {
     // Rarity thresholds for different tiers (e.g. [50, 75, 90])
     pub rarity_thresholds: Vec<u8>,
     
-    // Map of NFT indices to rarity scores (0-100)
+    // Map of NFT indices to rarity scores: one byte (0-100) per index, or two
+    // little-endian bytes (0-10000) per index when score_width is 2
     pub rarity_map: Vec<u8>,
     
     // Total number of mints analyzed
     pub total_mints: u64,
     
-    // Records of analyzed mints
+    // Records of analyzed mints (no longer written; see MintHistory)
     pub mint_records: Vec<MintRecord>,
     
     // Patterns detected in minting sequence
     pub mint_patterns: Vec<MintPattern>,
+    
+    // How NFT indices are encoded in this collection's metadata URIs
+    pub uri_template: UriTemplate,
+    
+    // Whether NFT indices are extracted from the URI or the asset name
+    pub index_source: IndexSource,
+    
+    // How NFT indices are encoded in this collection's asset names
+    pub name_template: NameTemplate,
+    
+    // Minters with the most recorded mints, most active first
+    pub top_minters: Vec<MinterRank>,
+    
+    // Number of rarity map entries per score (0-100), kept in sync by update_rarity_data
+    pub score_histogram: Vec<u32>,
+    
+    // Bytes per rarity map entry: 1 for u8 percentage scores (0 on older states), 2 for u16 basis points
+    pub score_width: u8,
+    
+    // Tier thresholds in basis points, used instead of rarity_thresholds when score_width is 2
+    pub precise_thresholds: Vec<u16>,
+    
+    // MPL Core collection whose creates record_mint accepts (default pubkey: none)
+    pub core_collection: Pubkey,
+    
+    // Known entries per precise tier (index 0 = Common), kept in sync by write_score when score_width is 2
+    pub precise_tier_counts: Vec<u32>,
+}
+
+impl RarityState {
+    // Whether scores are u16 basis points rather than u8 percentages
+    pub fn is_precise(&self) -> bool {
+        self.score_width == 2
+    }
+    
+    // Bytes per rarity map entry
+    pub fn entry_width(&self) -> usize {
+        if self.is_precise() { 2 } else { 1 }
+    }
+    
+    // Value stored for indices whose score is unknown
+    pub fn unknown_score(&self) -> u16 {
+        if self.is_precise() { UNKNOWN_PRECISE_SCORE } else { UNKNOWN_SCORE as u16 }
+    }
+    
+    // Highest meaningful score: 100, or 10000 for u16 scores
+    pub fn max_score(&self) -> u16 {
+        if self.is_precise() { MAX_PRECISE_SCORE } else { 100 }
+    }
+    
+    // Number of indices in the rarity map
+    pub fn map_len(&self) -> usize {
+        self.rarity_map.len() / self.entry_width()
+    }
+    
+    // Score stored for an index, if the index is in the rarity map
+    pub fn score(&self, index: u64) -> Option<u16> {
+        if self.is_precise() {
+            let start = (index as usize).checked_mul(2)?;
+            let bytes = self.rarity_map.get(start..start + 2)?;
+            Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+        } else {
+            self.rarity_map.get(index as usize).map(|score| *score as u16)
+        }
+    }
+    
+    // Store the score of an index; the rarity map must already have room for it
+    pub fn set_score(&mut self, index: usize, score: u16) {
+        if self.is_precise() {
+            self.rarity_map[index * 2..index * 2 + 2].copy_from_slice(&score.to_le_bytes());
+        } else {
+            self.rarity_map[index] = score as u8;
+        }
+    }
+    
+    // Scores of every index in the rarity map
+    pub fn scores(&self) -> impl Iterator<Item = u16> + '_ {
+        let width = self.entry_width();
+        self.rarity_map.chunks_exact(width).map(move |bytes| match width {
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]),
+            _ => bytes[0] as u16,
+        })
+    }
+    
+    // Tier thresholds in the state's score unit
+    pub fn thresholds(&self) -> Vec<u16> {
+        if self.is_precise() {
+            self.precise_thresholds.clone()
+        } else {
+            self.rarity_thresholds.iter().map(|threshold| *threshold as u16).collect()
+        }
+    }
+    
+    // Score as a percentage (0-100, or higher for out-of-range u8 scores)
+    pub fn score_percent(&self, score: u16) -> u8 {
+        if self.is_precise() {
+            (score / 100).min(u8::MAX as u16) as u8
+        } else {
+            score.min(u8::MAX as u16) as u8
+        }
+    }
+    
+    // Account size needed once the rarity map holds index_count entries, given the account's
+    // current size, including the score histogram and tier counts written along with it
+    pub fn space_for_map(&self, account_len: usize, index_count: usize) -> usize {
+        let map_growth = (index_count * self.entry_width()).saturating_sub(self.rarity_map.len());
+        let histogram_growth = 4 * SCORE_HISTOGRAM_BUCKETS.saturating_sub(self.score_histogram.len());
+        let tier_growth = if self.is_precise() {
+            4 * (self.precise_thresholds.len() + 1).saturating_sub(self.precise_tier_counts.len())
+        } else {
+            0
+        };
+        account_len + map_growth + histogram_growth + tier_growth
+    }
+    
+    // Percentage score converted to the state's score unit
+    pub fn score_from_percent(&self, percent: u8) -> u16 {
+        if self.is_precise() { percent as u16 * 100 } else { percent as u16 }
+    }
 }
 
 // Record of a single mint transaction
@@ -79,7 +297,7 @@ pub struct MintRecord {
     // The mint count at the time this NFT was minted
     pub mint_count: u64,
     
-    // Rarity score of this NFT (if known)
+    // Rarity score of this NFT as a percentage (if known)
     pub rarity_score: Option<u8>,
     
     // Address of the minter
@@ -89,6 +307,105 @@ pub struct MintRecord {
     pub timestamp: i64,
 }
 
+// Fixed-capacity ring buffer holding the latest mints recorded for a rarity state
+#[account]
+pub struct MintHistory {
+    // The rarity state this history belongs to
+    pub state: Pubkey,
+    
+    // The bump used for PDA derivation
+    pub bump: u8,
+    
+    // Maximum number of records kept
+    pub capacity: u32,
+    
+    // Position the next record is written to once the buffer is full (the oldest record)
+    pub head: u32,
+    
+    // Recorded mints; grows until it reaches capacity, then wraps around at head
+    pub records: Vec<MintRecord>,
+}
+
+impl MintHistory {
+    // Account size needed to hold the given number of records
+    pub fn space(records: usize) -> usize {
+        MINT_HISTORY_SIZE + records * MINT_RECORD_SIZE
+    }
+    
+    // Account size needed after the next record is written
+    pub fn space_for_next(&self) -> usize {
+        Self::space((self.records.len() + 1).min(self.capacity as usize))
+    }
+    
+    // Append a record, overwriting the oldest one once the buffer is full
+    pub fn push(&mut self, record: MintRecord) {
+        if self.records.len() < self.capacity as usize {
+            self.records.push(record);
+        } else {
+            self.records[self.head as usize] = record;
+            self.head = (self.head + 1) % self.capacity;
+        }
+    }
+    
+    // Records from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &MintRecord> {
+        let (newest, oldest) = self.records.split_at(self.head as usize);
+        oldest.iter().chain(newest.iter())
+    }
+}
+
+// Mint record of a single asset, stored at ["mint-record", state, asset_id] so it can
+// be looked up by asset ID
+#[account]
+pub struct MintRecordAccount {
+    // The rarity state the mint was recorded for
+    pub state: Pubkey,
+    
+    // The bump used for PDA derivation
+    pub bump: u8,
+    
+    // The recorded mint
+    pub record: MintRecord,
+}
+
+// Activity of a single minter for a rarity state, stored at ["minter-stats", state, minter]
+#[account]
+pub struct MinterStats {
+    // The rarity state these statistics belong to
+    pub state: Pubkey,
+    
+    // The minter these statistics describe
+    pub minter: Pubkey,
+    
+    // The bump used for PDA derivation
+    pub bump: u8,
+    
+    // Number of mints recorded with record_mint
+    pub mints: u64,
+    
+    // Number of validations that met the threshold
+    pub validations_passed: u64,
+    
+    // Number of batch items below the threshold (failed single validations abort and are not counted)
+    pub validations_failed: u64,
+    
+    // Highest score seen in a passed validation
+    pub best_score: u8,
+    
+    // Total validation fees paid, in lamports
+    pub fees_paid: u64,
+}
+
+// Leaderboard entry of the most active minters
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MinterRank {
+    // Address of the minter
+    pub minter: Pubkey,
+    
+    // Number of mints recorded for the minter
+    pub mints: u64,
+}
+
 // Pattern detected in mint sequence
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct MintPattern {
@@ -101,3 +418,364 @@ pub struct MintPattern {
     // Approximate probability of this pattern (0.0-1.0)
     pub probability: f64,
 }
+
+// Template describing where the NFT index sits in a collection's metadata URI,
+// e.g. base "https://gateway.pinit.io/ipfs/<cid>/" + "123" + suffix ".json"
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct UriTemplate {
+    // Prefix every URI of the collection starts with (an empty base means unconfigured)
+    pub base_uri: String,
+    
+    // Path segment after the base that holds the index (0 = first segment)
+    pub index_segment: u8,
+    
+    // Suffix every URI of the collection ends with (e.g. ".json")
+    pub suffix: String,
+    
+    // Exact width of zero-padded indices (0 = indices are not padded)
+    pub zero_pad: u8,
+    
+    // Whether the index is written in hexadecimal instead of decimal
+    pub hex_index: bool,
+}
+
+// Where the NFT index of an asset is read from
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexSource {
+    // Parse the index from the metadata URI using the URI template
+    #[default]
+    Uri,
+    
+    // Parse the index from the asset name using the name template
+    Name,
+}
+
+// Template describing asset names of the form "<prefix>#<n>", e.g. "Crowned Character #123"
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct NameTemplate {
+    // Text preceding the "#" in every asset name (e.g. "Crowned Character ")
+    pub prefix: String,
+    
+    // Subtracted from the parsed number (e.g. 1 for collections starting at #1)
+    pub index_offset: u32,
+}
+
+// Frequency table of every trait in a collection, used to score MPL Core assets
+// from their Attributes plugin
+#[account]
+pub struct TraitTable {
+    // The rarity state this table belongs to
+    pub state: Pubkey,
+    
+    // Update authority (address or collection) every scored asset must have
+    pub update_authority: Pubkey,
+    
+    // The bump used for PDA derivation
+    pub bump: u8,
+    
+    // Number of items the frequencies were counted over
+    pub total_supply: u32,
+    
+    // Number of items carrying each trait value
+    pub traits: Vec<TraitFrequency>,
+}
+
+// Number of items in the collection carrying a single trait value
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TraitFrequency {
+    // Attribute key (e.g. "Background")
+    pub trait_type: String,
+    
+    // Attribute value (e.g. "Gold")
+    pub value: String,
+    
+    // Number of items with this value
+    pub count: u32,
+}
+
+impl TraitFrequency {
+    // Serialized size of this entry
+    pub fn size(&self) -> usize {
+        4 + self.trait_type.len() + 4 + self.value.len() + 4
+    }
+    
+    // Whether two entries describe the same trait value
+    pub fn same_trait(&self, other: &TraitFrequency) -> bool {
+        self.trait_type == other.trait_type && self.value == other.value
+    }
+}
+
+impl TraitTable {
+    // Account size after uploading the given frequencies: entries that are already stored
+    // (and not cleared) are overwritten in place, so only new trait values add space
+    pub fn space_after_upload(&self, clear_existing: bool, traits: &[TraitFrequency]) -> usize {
+        let existing: &[TraitFrequency] = if clear_existing { &[] } else { &self.traits };
+        let mut space = TRAIT_TABLE_SIZE + existing.iter().map(|t| t.size()).sum::<usize>();
+        
+        for (i, frequency) in traits.iter().enumerate() {
+            let stored = existing.iter().chain(traits[..i].iter()).any(|t| t.same_trait(frequency));
+            if !stored {
+                space += frequency.size();
+            }
+        }
+        space
+    }
+}
+
+// Registry entry describing how to read the minted NFT index from a launchpad's
+// mint instructions, keyed by the launchpad's program ID
+#[account]
+pub struct LaunchpadAdapter {
+    // The launchpad program this adapter describes
+    pub program_id: Pubkey,
+    
+    // The bump used for PDA derivation
+    pub bump: u8,
+    
+    // Mint instructions of the launchpad and where their index is found
+    pub instructions: Vec<AdapterInstruction>,
+}
+
+impl LaunchpadAdapter {
+    // Account size needed to hold the given instructions
+    pub fn space(instructions: &[AdapterInstruction]) -> usize {
+        LAUNCHPAD_ADAPTER_SIZE + instructions.iter().map(|i| i.size()).sum::<usize>()
+    }
+}
+
+// A recognized mint instruction of a launchpad
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AdapterInstruction {
+    // Leading bytes of the instruction data identifying this instruction
+    pub discriminator: Vec<u8>,
+    
+    // Where the NFT index or minted asset is found
+    pub location: IndexLocation,
+}
+
+impl AdapterInstruction {
+    // Serialized size of this entry
+    pub fn size(&self) -> usize {
+        4 + self.discriminator.len() + 1 + 3
+    }
+}
+
+// Declarative rule for locating the NFT index of a mint instruction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum IndexLocation {
+    // Little-endian unsigned integer of `width` bytes at `offset` in the instruction data
+    InstructionData { offset: u16, width: u8 },
+    
+    // MPL Core asset at `account_index` in the instruction's accounts; the index is
+    // extracted from its name or URI using the state's templates
+    AssetAccount { account_index: u8 },
+}
+
+// Items checked by validate_batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum BatchItems {
+    // Rarity map indices
+    Indices(Vec<u64>),
+    
+    // Bubblegum nonces, mapped to indices through their predicted asset IDs
+    Nonces(Vec<u64>),
+    
+    // MPL Core assets passed as remaining accounts, indexed through the state's templates
+    Assets,
+}
+
+// How validate_batch treats items below the threshold
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    // Fail the instruction unless every item passes
+    RequireAll,
+    
+    // Report each item's outcome in the result without failing
+    ReportEach,
+}
+
+// Rank of every index in a collection (1 = rarest), stored at ["rank-table", state] alongside
+// the rarity map so validations can ask for "top N" or a percentile instead of a score
+#[account]
+pub struct RankTable {
+    // The rarity state this table belongs to
+    pub state: Pubkey,
+    
+    // The bump used for PDA derivation
+    pub bump: u8,
+    
+    // Bytes per rank: 2 (u16) or 4 (u32)
+    pub rank_width: u8,
+    
+    // Number of ranked items, the denominator for percentiles
+    pub total_ranked: u32,
+    
+    // Little-endian rank of each index, rank_width bytes per index (0 = not ranked)
+    pub ranks: Vec<u8>,
+}
+
+impl RankTable {
+    // Number of indices with room in the table
+    pub fn index_count(&self) -> usize {
+        self.ranks.len() / self.rank_width as usize
+    }
+    
+    // Rank stored for an index, if the index is in the table
+    pub fn rank(&self, index: u64) -> Option<u32> {
+        let width = self.rank_width as usize;
+        let start = (index as usize).checked_mul(width)?;
+        let bytes = self.ranks.get(start..start + width)?;
+        let mut rank = [0u8; 4];
+        rank[..width].copy_from_slice(bytes);
+        Some(u32::from_le_bytes(rank))
+    }
+    
+    // Whether indices up to (not including) end_index can be ranked. Every ranked item has its
+    // own rank, so there are at most total_ranked indices, 0 to total_ranked - 1.
+    pub fn holds_indices(&self, end_index: u64) -> bool {
+        end_index <= self.total_ranked as u64
+    }
+    
+    // Whether a rank is one of the table's ranks, 1 to total_ranked
+    pub fn is_valid_rank(&self, rank: u32) -> bool {
+        rank != 0 && rank <= self.total_ranked
+    }
+    
+    // Store the rank of an index; the table must already have room for it
+    pub fn set_rank(&mut self, index: usize, rank: u32) {
+        let width = self.rank_width as usize;
+        self.ranks[index * width..(index + 1) * width].copy_from_slice(&rank.to_le_bytes()[..width]);
+    }
+    
+    // Whether a rank meets a requirement
+    pub fn meets(&self, rank: u32, requirement: RankRequirement) -> bool {
+        match requirement {
+            RankRequirement::Top(max_rank) => rank <= max_rank,
+            RankRequirement::Percentile(max_bps) => {
+                rank as u64 * 10_000 <= max_bps as u64 * self.total_ranked as u64
+            }
+        }
+    }
+    
+    // Share of ranked items at or above a rank, in basis points rounded up (rank 1 of 10000 gives 1)
+    pub fn percentile_bps(&self, rank: u32) -> u16 {
+        if self.total_ranked == 0 {
+            return 10_000;
+        }
+        let bps = (rank as u64 * 10_000).div_ceil(self.total_ranked as u64);
+        bps.min(10_000) as u16
+    }
+}
+
+// How the payload of update_rarity_data_encoded is encoded; scores are in the state's score unit
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RarityEncoding {
+    // One byte per score, or two little-endian bytes for u16 scores (as update_rarity_data)
+    Raw,
+    
+    // Varint pairs: run length, then the score repeated that many times
+    RunLength,
+    
+    // Varint score count, a palette size byte (1-16), that many varint scores, then one
+    // 4-bit palette position per score, low nibble first
+    TierPacked,
+    
+    // Zigzag varint difference of each score from the previous one (the first from 0)
+    DeltaVarint,
+}
+
+// Score of a single index, written by update_rarity_entries
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct RarityEntry {
+    pub index: u32,
+    
+    // Score in the state's score unit
+    pub score: u16,
+}
+
+// Rank an NFT must reach in validate_rank
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RankRequirement {
+    // Rank at most N (e.g. Top(100) for the 100 rarest items)
+    Top(u32),
+    
+    // Within the rarest share of the collection, in basis points (e.g. Percentile(100) for the top 1%)
+    Percentile(u16),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank_table(rank_width: u8, total_ranked: u32) -> RankTable {
+        RankTable {
+            state: Pubkey::default(),
+            bump: 255,
+            rank_width,
+            total_ranked,
+            ranks: vec![0; total_ranked as usize * rank_width as usize],
+        }
+    }
+
+    #[test]
+    fn index_bounds() {
+        let table = rank_table(2, 100);
+        assert!(table.holds_indices(0));
+        assert!(table.holds_indices(99));
+        assert!(table.holds_indices(100));
+        assert!(!table.holds_indices(101));
+        
+        assert_eq!(table.rank(0), Some(0));
+        assert_eq!(table.rank(99), Some(0));
+        assert_eq!(table.rank(100), None);
+        assert_eq!(table.rank(u64::MAX), None);
+    }
+
+    #[test]
+    fn rank_bounds() {
+        let table = rank_table(2, 100);
+        assert!(!table.is_valid_rank(0));
+        assert!(table.is_valid_rank(1));
+        assert!(table.is_valid_rank(100));
+        assert!(!table.is_valid_rank(101));
+    }
+
+    #[test]
+    fn ranks_beyond_u16() {
+        // u32 ranks are not limited to u16 indices
+        let total_ranked = u16::MAX as u32 + 10;
+        let mut table = rank_table(4, total_ranked);
+        let last = total_ranked as usize - 1;
+        table.set_rank(0, total_ranked);
+        table.set_rank(last, 1);
+        assert_eq!(table.rank(0), Some(total_ranked));
+        assert_eq!(table.rank(last as u64), Some(1));
+        assert_eq!(table.rank(total_ranked as u64), None);
+        assert!(table.holds_indices(total_ranked as u64));
+        assert!(!table.holds_indices(total_ranked as u64 + 1));
+    }
+
+    #[test]
+    fn percentiles_at_the_ends() {
+        let table = rank_table(2, 10_000);
+        assert_eq!(table.percentile_bps(1), 1);
+        assert_eq!(table.percentile_bps(10_000), 10_000);
+        
+        let table = rank_table(2, 3);
+        assert_eq!(table.percentile_bps(1), 3_334);
+        assert_eq!(table.percentile_bps(3), 10_000);
+        assert_eq!(rank_table(2, 0).percentile_bps(1), 10_000);
+    }
+
+    #[test]
+    fn requirements_at_the_boundary() {
+        let table = rank_table(2, 200);
+        assert!(table.meets(10, RankRequirement::Top(10)));
+        assert!(!table.meets(11, RankRequirement::Top(10)));
+        
+        // Top 1% of 200 items is ranks 1 and 2
+        assert!(table.meets(2, RankRequirement::Percentile(100)));
+        assert!(!table.meets(3, RankRequirement::Percentile(100)));
+        assert!(table.meets(200, RankRequirement::Percentile(10_000)));
+    }
+}
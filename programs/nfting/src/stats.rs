@@ -0,0 +1,146 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{MinterRank, MinterStats, MAX_TOP_MINTERS};
+use crate::ErrorCode;
+
+// Find the minter's ["minter-stats", state, minter] PDA among the remaining accounts. Validation
+// instructions take it there, so callers that don't track statistics pay nothing for it.
+pub fn find_minter_stats<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    state: &Pubkey,
+    minter: &Pubkey,
+) -> Option<&'a AccountInfo<'info>> {
+    if remaining_accounts.is_empty() {
+        return None;
+    }
+
+    let (address, _) = Pubkey::find_program_address(
+        &[b"minter-stats", state.as_ref(), minter.as_ref()],
+        &crate::id(),
+    );
+    remaining_accounts.iter().find(|account| account.key() == address)
+}
+
+// Apply an update to the minter statistics account, if one was passed and has been created
+pub fn update_minter_stats(
+    minter_stats: Option<&AccountInfo>,
+    update: impl FnOnce(&mut MinterStats),
+) -> Result<()> {
+    let account = match minter_stats {
+        Some(account) if *account.owner == crate::id() => account,
+        _ => return Ok(()),
+    };
+    if !account.is_writable {
+        msg!("Minter statistics account {} must be writable", account.key());
+        return Err(error!(ErrorCode::InvalidMinterStats));
+    }
+
+    let mut stats = MinterStats::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    update(&mut stats);
+    stats.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// Record the outcome of a validation in the minter's statistics (score as a percentage)
+pub fn record_validation(minter_stats: &mut MinterStats, passed: bool, score: u8, fee: u64) {
+    if passed {
+        minter_stats.validations_passed += 1;
+        minter_stats.best_score = minter_stats.best_score.max(score);
+    } else {
+        minter_stats.validations_failed += 1;
+    }
+    record_fee(minter_stats, fee);
+}
+
+// Add a fee the minter was charged to their statistics
+pub fn record_fee(minter_stats: &mut MinterStats, fee: u64) {
+    minter_stats.fees_paid += fee;
+}
+
+// Update a minter's mint count on the leaderboard, keeping the most active
+// minters first. Once the board is full a minter only enters by passing the last entry.
+pub fn update_leaderboard(top_minters: &mut Vec<MinterRank>, minter: Pubkey, mints: u64) {
+    if let Some(rank) = top_minters.iter_mut().find(|r| r.minter == minter) {
+        rank.mints = mints;
+    } else if top_minters.len() < MAX_TOP_MINTERS {
+        top_minters.push(MinterRank { minter, mints });
+    } else if let Some(rank) = top_minters.iter_mut().min_by_key(|r| r.mints) {
+        if mints <= rank.mints {
+            return;
+        }
+        *rank = MinterRank { minter, mints };
+    }
+
+    top_minters.sort_by_key(|r| std::cmp::Reverse(r.mints));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minter_stats() -> MinterStats {
+        MinterStats {
+            state: Pubkey::new_unique(),
+            minter: Pubkey::new_unique(),
+            bump: 255,
+            mints: 0,
+            validations_passed: 0,
+            validations_failed: 0,
+            best_score: 0,
+            fees_paid: 0,
+        }
+    }
+
+    #[test]
+    fn validations_and_fees() {
+        let mut stats = minter_stats();
+        record_validation(&mut stats, true, 80, 100);
+        record_validation(&mut stats, true, 60, 100);
+        record_validation(&mut stats, false, 95, 0);
+        record_fee(&mut stats, 50);
+        
+        assert_eq!(stats.validations_passed, 2);
+        assert_eq!(stats.validations_failed, 1);
+        // Failed validations never raise the best score
+        assert_eq!(stats.best_score, 80);
+        assert_eq!(stats.fees_paid, 250);
+    }
+
+    #[test]
+    fn leaderboard_fills_and_sorts() {
+        let mut top_minters = Vec::new();
+        let minters: Vec<Pubkey> = (0..MAX_TOP_MINTERS).map(|_| Pubkey::new_unique()).collect();
+        for (i, minter) in minters.iter().enumerate() {
+            update_leaderboard(&mut top_minters, *minter, i as u64 + 1);
+        }
+        assert_eq!(top_minters.len(), MAX_TOP_MINTERS);
+        assert_eq!(top_minters[0].minter, minters[MAX_TOP_MINTERS - 1]);
+        assert_eq!(top_minters[MAX_TOP_MINTERS - 1].mints, 1);
+        
+        // An existing minter's count is updated in place and re-sorted
+        update_leaderboard(&mut top_minters, minters[0], 100);
+        assert_eq!(top_minters.len(), MAX_TOP_MINTERS);
+        assert_eq!(top_minters[0].minter, minters[0]);
+        assert_eq!(top_minters[0].mints, 100);
+    }
+
+    #[test]
+    fn full_leaderboard_replaces_the_last_entry() {
+        let mut top_minters = Vec::new();
+        for i in 0..MAX_TOP_MINTERS {
+            update_leaderboard(&mut top_minters, Pubkey::new_unique(), i as u64 + 2);
+        }
+        
+        // Tying the last entry is not enough to enter
+        let tied = Pubkey::new_unique();
+        update_leaderboard(&mut top_minters, tied, 2);
+        assert!(top_minters.iter().all(|rank| rank.minter != tied));
+        
+        let newcomer = Pubkey::new_unique();
+        update_leaderboard(&mut top_minters, newcomer, 3);
+        assert_eq!(top_minters.len(), MAX_TOP_MINTERS);
+        assert!(top_minters.iter().any(|rank| rank.minter == newcomer));
+        assert!(top_minters.iter().all(|rank| rank.mints >= 3));
+        assert!(top_minters.windows(2).all(|pair| pair[0].mints >= pair[1].mints));
+    }
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::state::Mint;
+use spl_token_metadata_interface::state::TokenMetadata;
+
+use crate::ErrorCode;
+
+// Read the token-metadata extension stored in a Token-2022 mint account.
+// Only metadata kept on the mint itself is supported, so the extension's
+// mint must be the account it was read from.
+pub fn load_token_metadata(mint_account: &AccountInfo) -> Result<TokenMetadata> {
+    let data = mint_account.try_borrow_data()?;
+
+    let mint = StateWithExtensions::<Mint>::unpack(&data).map_err(|_| {
+        msg!("Account is not a Token-2022 mint");
+        error!(ErrorCode::InvalidToken2022Mint)
+    })?;
+
+    let token_metadata = mint.get_variable_len_extension::<TokenMetadata>().map_err(|_| {
+        msg!("Mint has no token-metadata extension");
+        error!(ErrorCode::InvalidToken2022Mint)
+    })?;
+
+    if token_metadata.mint != mint_account.key() {
+        msg!("Token metadata belongs to mint {}", token_metadata.mint);
+        return Err(error!(ErrorCode::InvalidToken2022Mint));
+    }
+
+    Ok(token_metadata)
+}
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::state::TraitTable;
+use crate::ErrorCode;
+
+// Compute the statistical rarity score (0 to max_score) of a set of (trait type, value) pairs.
+// Each trait's probability is its frequency in the collection; the score is
+// max_score * (1 - geometric mean of the probabilities), so rarer combinations score higher.
+pub fn statistical_rarity_score<'a>(
+    table: &TraitTable,
+    attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    max_score: u16,
+) -> Result<u16> {
+    if table.total_supply == 0 || table.traits.is_empty() {
+        msg!("Trait table has no frequency data");
+        return Err(error!(ErrorCode::NoRarityData));
+    }
+
+    // Sum of log probabilities, to avoid underflow of the raw product
+    let mut log_probability_sum = 0.0f64;
+    let mut trait_count = 0usize;
+
+    for (trait_type, value) in attributes {
+        let frequency = table
+            .traits
+            .iter()
+            .find(|t| t.trait_type == trait_type && t.value == value);
+
+        let count = match frequency {
+            Some(frequency) if frequency.count > 0 => frequency.count,
+            _ => {
+                msg!("Unknown trait {}: {}", trait_type, value);
+                return Err(error!(ErrorCode::UnknownTrait));
+            }
+        };
+
+        let probability = (count as f64 / table.total_supply as f64).min(1.0);
+        msg!("Trait {}: {} ({} of {})", trait_type, value, count, table.total_supply);
+
+        log_probability_sum += probability.ln();
+        trait_count += 1;
+    }
+
+    if trait_count == 0 {
+        msg!("Asset has no attributes");
+        return Err(error!(ErrorCode::NoAttributes));
+    }
+
+    let geometric_mean = (log_probability_sum / trait_count as f64).exp();
+    let score = (max_score as f64 * (1.0 - geometric_mean)).round().clamp(0.0, max_score as f64);
+
+    Ok(score as u16)
+}
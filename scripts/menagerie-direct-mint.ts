@@ -17,7 +17,7 @@ import {
 import * as fs from 'fs';
 import * as path from 'path';
 import axios from 'axios';
-import { Program, AnchorProvider, web3, BN, utils, Wallet } from '@coral-xyz/anchor';
+import { Program, AnchorProvider, web3, utils, Wallet } from '@coral-xyz/anchor';
 import * as dotenv from 'dotenv';
 import * as bs58 from 'bs58';
 import { Buffer } from 'buffer';
@@ -195,9 +195,11 @@ async function mintNFT(nftIndex: number) {
     );
     
     const feeReceiver = new PublicKey("89VB5UmvopuCFmp5Mf8YPX28fGvvqn79afCgouQuPyhY");
+    // Minimum score (u16) in the state's score unit: whole percent, or basis points on precise states
+    const minScore = 50;
   try {
       const ix = await program.methods
-        .validateMintCore(new BN(50))
+        .validateMintCore(minScore, false)
         .accounts({
           assetAccount: nftMintKeypair.publicKey,
           state: nftBeaterPDA,
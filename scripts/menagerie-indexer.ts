@@ -27,6 +27,120 @@ const METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt
 const FEE_RECEIVER = new PublicKey("89VB5UmvopuCFmp5Mf8YPX28fGvvqn79afCgouQuPyhY");
 const IPFS_METADATA_BASE_URI = "https://gateway.pinit.io/ipfs/Qmd2mt5hpF9d9QMDhpX9SecoPsvdpqcGVnP7ETfxB6hrr3";
 
+// Largest encoded rarity payload sent in a single update_rarity_data_encoded transaction
+const MAX_ENCODED_PAYLOAD = 900;
+
+// Most scores decoded per transaction, so the state grows by well under 10 KB per call
+const MAX_CHUNK_SCORES = 8192;
+
+// Score the program reads as "no data" for a one-byte (percent) rarity map
+const UNKNOWN_SCORE = 0xff;
+
+// Largest palette of a tier-packed payload
+const MAX_PALETTE_SIZE = 16;
+
+type RarityEncoding = { raw: {} } | { runLength: {} } | { tierPacked: {} } | { deltaVarint: {} };
+
+interface EncodedChunk {
+  startIndex: number;
+  count: number;
+  encoding: RarityEncoding;
+  payload: Buffer;
+}
+
+// Append an unsigned LEB128 varint
+function pushVarint(bytes: number[], value: number): void {
+  while (value >= 0x80) {
+    bytes.push((value & 0x7f) | 0x80);
+    value = Math.floor(value / 128);
+  }
+  bytes.push(value);
+}
+
+// (run length, score) varint pairs
+function encodeRunLength(scores: number[]): number[] {
+  const bytes: number[] = [];
+  let i = 0;
+  while (i < scores.length) {
+    let run = 1;
+    while (i + run < scores.length && scores[i + run] === scores[i]) {
+      run++;
+    }
+    pushVarint(bytes, run);
+    pushVarint(bytes, scores[i]);
+    i += run;
+  }
+  return bytes;
+}
+
+// Count, palette of up to 16 scores, then one 4-bit palette position per score (low nibble
+// first). Returns null when the scores have too many distinct values.
+function encodeTierPacked(scores: number[]): number[] | null {
+  const palette = Array.from(new Set(scores));
+  if (palette.length > MAX_PALETTE_SIZE) {
+    return null;
+  }
+  const bytes: number[] = [];
+  pushVarint(bytes, scores.length);
+  bytes.push(palette.length);
+  palette.forEach(score => pushVarint(bytes, score));
+  for (let i = 0; i < scores.length; i += 2) {
+    const low = palette.indexOf(scores[i]);
+    const high = i + 1 < scores.length ? palette.indexOf(scores[i + 1]) : 0;
+    bytes.push(low | (high << 4));
+  }
+  return bytes;
+}
+
+// Zigzag varint differences, starting from a previous score of 0
+function encodeDeltaVarint(scores: number[]): number[] {
+  const bytes: number[] = [];
+  let previous = 0;
+  for (const score of scores) {
+    const delta = score - previous;
+    pushVarint(bytes, delta >= 0 ? delta * 2 : -delta * 2 - 1);
+    previous = score;
+  }
+  return bytes;
+}
+
+// Encode a run of one-byte scores in whichever of the program's encodings is smallest
+function encodeSmallest(scores: number[]): { encoding: RarityEncoding; payload: Buffer } {
+  const candidates: { encoding: RarityEncoding; bytes: number[] | null }[] = [
+    { encoding: { raw: {} }, bytes: scores },
+    { encoding: { runLength: {} }, bytes: encodeRunLength(scores) },
+    { encoding: { tierPacked: {} }, bytes: encodeTierPacked(scores) },
+    { encoding: { deltaVarint: {} }, bytes: encodeDeltaVarint(scores) },
+  ];
+  let best = candidates[0];
+  for (const candidate of candidates) {
+    if (candidate.bytes !== null && candidate.bytes.length < best.bytes!.length) {
+      best = candidate;
+    }
+  }
+  return { encoding: best.encoding, payload: Buffer.from(best.bytes!) };
+}
+
+// Split scores into chunks of at most maxCount scores whose smallest encoding fits in
+// maxPayload bytes, halving a chunk until it fits
+function encodeRarityChunks(scores: number[], maxPayload: number, maxCount: number): EncodedChunk[] {
+  const chunks: EncodedChunk[] = [];
+  let startIndex = 0;
+  
+  while (startIndex < scores.length) {
+    let count = Math.min(maxCount, scores.length - startIndex);
+    let encoded = encodeSmallest(scores.slice(startIndex, startIndex + count));
+    while (encoded.payload.length > maxPayload) {
+      count = Math.ceil(count / 2);
+      encoded = encodeSmallest(scores.slice(startIndex, startIndex + count));
+    }
+    chunks.push({ startIndex, count, ...encoded });
+    startIndex += count;
+  }
+  
+  return chunks;
+}
+
 // NFT Metadata interface
 interface NFTMetadata {
   name: string;
@@ -198,39 +312,41 @@ export class MenagerieIndexer {
     // Find the largest index in our rarity data
     const maxIndex = Math.max(...Array.from(this.rarityScores.keys()));
     
-    // Create a rarity array with all indices from 0 to maxIndex
-    const rarityArray = new Array(maxIndex + 1).fill(0);
+    // Create a rarity array with all indices from 0 to maxIndex, leaving indices we have no
+    // score for unknown so they don't count towards the distribution
+    const rarityArray = new Array(maxIndex + 1).fill(UNKNOWN_SCORE);
     
     // Fill in the rarity scores we have
     this.rarityScores.forEach((score, index) => {
       rarityArray[index] = Math.floor(score);
     });
     
-    // Split into chunks to avoid transaction size limits
-    const chunkSize = 1000;
-    for (let i = 0; i < rarityArray.length; i += chunkSize) {
-      const chunk = rarityArray.slice(i, i + chunkSize);
+    // Split into chunks that fit in a transaction, each in its smallest encoding
+    const chunks = encodeRarityChunks(rarityArray, MAX_ENCODED_PAYLOAD, MAX_CHUNK_SCORES);
+    for (let c = 0; c < chunks.length; c++) {
+      const { startIndex, count, encoding, payload } = chunks[c];
       
       try {
         await this.program.methods
-          .updateRarityData(new BN(i), Buffer.from(chunk))
+          .updateRarityDataEncoded(new BN(startIndex), encoding, payload)
           .accounts({
             state: await this.getNftBeaterPda(),
             authority: this.wallet.publicKey,
+            merkleTree: MERKLE_TREE_ADDRESS,
             feeReceiver: FEE_RECEIVER,
             systemProgram: SystemProgram.programId,
           })
           .signers([this.wallet])
           .rpc();
         
-        console.log(`Updated rarity data for indices ${i} to ${i + chunk.length - 1}`);
+        console.log(`Updated rarity data for indices ${startIndex} to ${startIndex + count - 1} (${Object.keys(encoding)[0]}, ${payload.length} bytes)`);
       } catch (error) {
-        console.error(`Error updating rarity data for chunk starting at ${i}:`, error);
+        console.error(`Error updating rarity data for chunk starting at ${startIndex}:`, error);
         throw error;
       }
       
       // Add delay between chunks
-      if (i + chunkSize < rarityArray.length) {
+      if (c + 1 < chunks.length) {
         await new Promise(resolve => setTimeout(resolve, 1000));
       }
     }
@@ -305,20 +421,65 @@ export class MenagerieIndexer {
     console.log('Fetching mint statistics...');
     
     try {
-      await this.program.methods
-        .getMintStatistics()
+      const stats = await this.program.methods
+        .getMintStatistics(false)
         .accounts({
           state: await this.getNftBeaterPda(),
+          mintHistory: null,
         })
-        .rpc();
+        .view();
       
-      console.log('Mint statistics printed to program logs');
+      console.log(`Total mints analyzed: ${stats.totalMints.toString()}`);
+      console.log(`Records with rarity scores: ${stats.recordsWithRarity}`);
+      console.log(`Tier counts: ${stats.tierCounts.join(', ')}`);
+      for (const pattern of stats.topPatterns) {
+        console.log(
+          `Difference of ${pattern.difference.toString()}: ${pattern.occurrences.toString()} occurrences ` +
+          `(approx. ${(pattern.probability * 100).toFixed(1)}% probability)`
+        );
+      }
+      for (const rank of stats.topMinters) {
+        console.log(`${rank.minter.toBase58()}: ${rank.mints.toString()} mints`);
+      }
     } catch (error) {
       console.error('Error getting mint statistics:', error);
       throw error;
     }
   }
   
+  /**
+   * Get the on-chain rarity distribution and the probability that a mint meets a threshold
+   */
+  async getRarityDistribution(minRarityPercentage: number): Promise<number> {
+    console.log(`Fetching rarity distribution for a ${minRarityPercentage}% threshold...`);
+    
+    try {
+      const distribution = await this.program.methods
+        .getRarityDistribution(minRarityPercentage)
+        .accounts({
+          state: await this.getNftBeaterPda(),
+        })
+        .view();
+      
+      const probability = distribution.passProbabilityBps / 10_000;
+      console.log(`NFTs in rarity map: ${distribution.total} (${distribution.known} scored)`);
+      console.log(`Tier counts: ${distribution.tierCounts.join(', ')}`);
+      distribution.tierPassProbabilityBps.forEach((bps: number, tier: number) => {
+        console.log(`Chance of tier ${tier} or better: ${(bps / 100).toFixed(2)}%`);
+      });
+      console.log(`NFTs scoring at least ${minRarityPercentage}: ${distribution.passing}`);
+      console.log(`Estimated success probability: ${(probability * 100).toFixed(2)}%`);
+      if (probability > 0) {
+        console.log(`Expected attempts per successful mint: ${(1 / probability).toFixed(1)}`);
+      }
+      
+      return probability;
+    } catch (error) {
+      console.error('Error getting rarity distribution:', error);
+      throw error;
+    }
+  }
+  
   /**
    * Get the tree config PDA
    */
@@ -330,28 +491,32 @@ export class MenagerieIndexer {
   }
   
   /**
-   * Validate a mint with minimum rarity threshold
+   * Validate the mint at a Bubblegum nonce with minimum rarity threshold. A dry run is
+   * simulated instead of sent: it charges no fee and reports the score without failing.
    */
-  async validateMint(minRarityPercentage: number, mintIndex?: number): Promise<boolean> {
-    console.log(`Validating mint with minimum rarity: ${minRarityPercentage}%`);
+  async validateMint(minRarityPercentage: number, numMinted: number, dryRun = false): Promise<boolean> {
+    console.log(`Validating mint ${numMinted} with minimum rarity: ${minRarityPercentage}${dryRun ? ' (dry run)' : ''}`);
     
     try {
-      await this.program.methods
-        .validateMint(
-          mintIndex ? new BN(mintIndex) : null,
-          minRarityPercentage
-        )
+      const builder = this.program.methods
+        .validateMint(minRarityPercentage, new BN(numMinted), dryRun)
         .accounts({
           state: await this.getNftBeaterPda(),
           merkleTree: MERKLE_TREE_ADDRESS,
-          treeConfig: await this.getTreeConfigPDA(),
           minter: this.wallet.publicKey,
           feeReceiver: FEE_RECEIVER,
           systemProgram: SystemProgram.programId,
         })
-        .signers([this.wallet])
-        .rpc();
+        .signers([this.wallet]);
       
+      if (dryRun) {
+        const result = await builder.view();
+        console.log(`Index: ${result.index ?? 'unknown'}, score: ${result.score}, tier: ${result.tier}`);
+        console.log(`Dry run ${result.passed ? 'meets' : 'misses'} the threshold`);
+        return result.passed;
+      }
+      
+      await builder.rpc();
       console.log('Mint validation successful!');
       return true;
     } catch (error: any) {
@@ -468,11 +633,16 @@ async function main() {
       await indexer.getMintStatistics();
       break;
       
+    case 'distribution':
+      await indexer.getRarityDistribution(parseInt(process.argv[3] || '50'));
+      break;
+      
     case 'validate':
       const minRarity = parseInt(process.argv[3] || '50');
-      const specificIndex = process.argv[4] ? parseInt(process.argv[4]) : undefined;
+      const numMinted = parseInt(process.argv[4] || '0');
+      const dryRun = process.argv.includes('--dry-run');
       
-      const result = await indexer.validateMint(minRarity, specificIndex);
+      const result = await indexer.validateMint(minRarity, numMinted, dryRun);
       console.log(`Validation result: ${result ? 'PASS' : 'FAIL'}`);
       break;
       
@@ -485,7 +655,9 @@ Usage:
   update                       - Update on-chain rarity data
   analyze <tx> <mint> [index]  - Analyze a mint transaction
   stats                        - Get mint statistics
-  validate <rarity> [index]    - Validate a mint against minimum rarity
+  distribution [rarity]        - Show rarity distribution and success probability
+  validate <rarity> <nonce> [--dry-run]
+                               - Validate a mint against minimum rarity (--dry-run simulates it for free)
   help                         - Show this help message
       `);
       break;